@@ -0,0 +1,11 @@
+//! Agent loops built on top of the `LLM` trait.
+
+pub mod plan_execute;
+pub mod react;
+pub mod reflection;
+#[cfg(test)]
+mod test_support;
+
+pub use plan_execute::PlanAndExecuteAgent;
+pub use react::ReActAgent;
+pub use reflection::ReflectionAgent;
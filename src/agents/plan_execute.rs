@@ -0,0 +1,169 @@
+//! Plan-and-execute: one model drafts a numbered plan, another carries out
+//! each step (optionally calling tools), and the results feed a final answer.
+
+use anyhow::Result;
+
+use super::react::{parse_step, Step};
+use crate::llm::{LLMRequest, Message, LLM};
+use crate::tools::ToolRegistry;
+
+/// Tool calls the executor may make while working on a single step.
+const MAX_TOOL_CALLS_PER_STEP: usize = 5;
+
+const PLANNER_PROMPT: &str = "\
+You are a planner. Break the user's goal into a short numbered list of concrete steps,
+one per line (\"1. ...\", \"2. ...\"). Output only the list.";
+
+const EXECUTOR_PROMPT: &str = "\
+You are carrying out one step of a larger plan. To use a tool, reply with exactly:
+
+Action: the tool name
+Action Input: the input to pass to the tool
+
+and you will receive an Observation. When the step is done, reply with:
+
+Final Answer: the result of this step";
+
+/// Parses `1. step` / `2) step` lines from the planner's output.
+fn parse_plan(text: &str) -> Vec<String> {
+    text.lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let rest = line.trim_start_matches(|c: char| c.is_ascii_digit());
+            if rest.len() == line.len() {
+                return None;
+            }
+            let step = rest.strip_prefix('.').or_else(|| rest.strip_prefix(')'))?.trim();
+            (!step.is_empty()).then(|| step.to_string())
+        })
+        .collect()
+}
+
+pub struct PlanAndExecuteAgent {
+    pub planner_llm: Box<dyn LLM>,
+    pub executor_llm: Box<dyn LLM>,
+    pub tools: ToolRegistry,
+}
+
+impl PlanAndExecuteAgent {
+    async fn plan(&self, goal: &str) -> Result<Vec<String>> {
+        let system_prompt = if self.tools.is_empty() {
+            PLANNER_PROMPT.to_string()
+        } else {
+            format!("{}\n\nThe executor can use these tools:\n{}", PLANNER_PROMPT, self.tools.describe())
+        };
+        let request = LLMRequest {
+            system_prompt,
+            messages: vec![Message::user(goal)],
+            model: None,
+        };
+        let response = self.planner_llm.invoke(&request).await?;
+        let steps = parse_plan(&response.content);
+        if steps.is_empty() {
+            anyhow::bail!("Planner did not return a numbered list of steps:\n{}", response.content);
+        }
+        Ok(steps)
+    }
+
+    /// Carries out one step, letting the executor call tools until it reports a result.
+    async fn execute_step(&self, goal: &str, step: &str, results: &[String]) -> Result<String> {
+        let system_prompt = format!("{}\n\nAvailable tools:\n{}", EXECUTOR_PROMPT, self.tools.describe());
+        let mut context = format!("Overall goal: {}\n", goal);
+        for (i, result) in results.iter().enumerate() {
+            context.push_str(&format!("Result of step {}: {}\n", i + 1, result));
+        }
+        let mut messages = vec![Message::user(&format!("{}\nCurrent step: {}", context, step))];
+
+        for _ in 0..=MAX_TOOL_CALLS_PER_STEP {
+            let request = LLMRequest {
+                system_prompt: system_prompt.clone(),
+                messages: messages.clone(),
+                model: None,
+            };
+            let response = self.executor_llm.invoke(&request).await?;
+            let observation = match parse_step(&response.content) {
+                Step::FinalAnswer(answer) => return Ok(answer),
+                // Without an action, treat the whole reply as the step's result.
+                Step::Invalid => return Ok(response.content.trim().to_string()),
+                Step::Action { tool, input } => match self.tools.call(&tool, &input).await {
+                    Ok(output) => output,
+                    Err(e) => format!("Error: {:#}", e),
+                },
+            };
+            messages.push(Message::assistant(&response.content));
+            messages.push(Message::user(&format!("Observation: {}", observation)));
+        }
+        anyhow::bail!("Step '{}' did not finish within {} tool calls", step, MAX_TOOL_CALLS_PER_STEP)
+    }
+
+    /// Plans `goal`, executes each step in order, and asks the executor to
+    /// synthesize a conclusion from the collected results.
+    pub async fn run(&mut self, goal: &str) -> Result<String> {
+        let steps = self.plan(goal).await?;
+        let mut results: Vec<String> = Vec::with_capacity(steps.len());
+        for step in &steps {
+            let result = self.execute_step(goal, step, &results).await?;
+            results.push(result);
+        }
+
+        let mut summary = format!("Goal: {}\n\n", goal);
+        for (step, result) in steps.iter().zip(&results) {
+            summary.push_str(&format!("Step: {}\nResult: {}\n\n", step, result));
+        }
+        summary.push_str("Using these results, write the final answer to the goal.");
+        let request = LLMRequest {
+            system_prompt: "You synthesize the results of a completed plan into a clear final answer.".to_string(),
+            messages: vec![Message::user(&summary)],
+            model: None,
+        };
+        Ok(self.executor_llm.invoke(&request).await?.content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::test_support::{Scripted, Upper};
+
+    #[test]
+    fn parses_numbered_steps() {
+        let plan = "Here's the plan:\n1. Gather the data\n2) Clean it\n   10. Chart it  \n- a bullet\nStep 4: not numbered\n5.\n6)   \n7 no separator";
+        assert_eq!(parse_plan(plan), ["Gather the data", "Clean it", "Chart it"]);
+        assert!(parse_plan("No steps at all.").is_empty());
+    }
+
+    #[tokio::test]
+    async fn plans_executes_each_step_and_synthesizes() {
+        let planner = Scripted::new(&["1. Shout the greeting\n2. Count its letters"]);
+        let executor = Scripted::new(&[
+            "Action: upper\nAction Input: hello",
+            "Final Answer: HELLO",
+            "It has 5 letters.",
+            "The greeting is HELLO, with 5 letters.",
+        ]);
+        let mut tools = ToolRegistry::new();
+        tools.register(Box::new(Upper));
+        let mut agent = PlanAndExecuteAgent { planner_llm: Box::new(planner.clone()), executor_llm: Box::new(executor.clone()), tools };
+
+        assert_eq!(agent.run("greet loudly").await.unwrap(), "The greeting is HELLO, with 5 letters.");
+        assert!(planner.requests()[0].system_prompt.contains("- upper: Upper-cases the input"));
+        let requests = executor.requests();
+        assert_eq!(requests.len(), 4);
+        assert_eq!(requests[1].messages.last().unwrap().text_content(), "Observation: HELLO");
+        // Later steps see earlier results, and the synthesis sees every step.
+        assert!(requests[2].messages[0].text_content().contains("Result of step 1: HELLO\n\nCurrent step: Count its letters"));
+        let summary = requests[3].messages[0].text_content();
+        assert!(summary.contains("Step: Shout the greeting\nResult: HELLO") && summary.contains("Result: It has 5 letters."), "{}", summary);
+    }
+
+    #[tokio::test]
+    async fn a_plan_without_steps_is_an_error() {
+        let mut agent = PlanAndExecuteAgent {
+            planner_llm: Box::new(Scripted::new(&["I'd rather not."])),
+            executor_llm: Box::new(Scripted::new(&[])),
+            tools: ToolRegistry::new(),
+        };
+        let error = agent.run("anything").await.unwrap_err();
+        assert!(error.to_string().starts_with("Planner did not return a numbered list of steps"), "{}", error);
+    }
+}
@@ -0,0 +1,161 @@
+//! The ReAct (Reason + Act) loop: the model alternates between thinking,
+//! calling a tool, and reading the tool's observation until it can answer.
+
+use anyhow::Result;
+
+use crate::llm::{LLMRequest, Message, LLM};
+use crate::tools::ToolRegistry;
+
+const FORMAT_INSTRUCTIONS: &str = "\
+Solve the task by reasoning step by step. Use exactly this format:
+
+Thought: your reasoning about what to do next
+Action: the name of one tool to use
+Action Input: the input to pass to the tool
+
+You will then receive an Observation with the tool's output. Repeat
+Thought/Action/Action Input as many times as needed. When you know the answer, write:
+
+Thought: I now know the final answer
+Final Answer: the answer to the task";
+
+/// One parsed model turn.
+#[derive(Debug, PartialEq)]
+pub(super) enum Step {
+    FinalAnswer(String),
+    Action { tool: String, input: String },
+    Invalid,
+}
+
+/// Extracts the final answer or the next action from a model turn.
+pub(super) fn parse_step(text: &str) -> Step {
+    // Ignore anything after a hallucinated observation; only the tool may supply one.
+    let text = text.split("Observation:").next().unwrap_or_default();
+
+    if let Some((_, answer)) = text.split_once("Final Answer:") {
+        return Step::FinalAnswer(answer.trim().to_string());
+    }
+
+    let mut tool = None;
+    let mut input = None;
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix("Action Input:") {
+            input = Some(rest.trim().to_string());
+        } else if let Some(rest) = line.strip_prefix("Action:") {
+            tool = Some(rest.trim().to_string());
+        }
+    }
+    match (tool, input) {
+        (Some(tool), input) if !tool.is_empty() => Step::Action {
+            tool,
+            input: input.unwrap_or_default(),
+        },
+        _ => Step::Invalid,
+    }
+}
+
+pub struct ReActAgent {
+    pub llm: Box<dyn LLM>,
+    pub tools: ToolRegistry,
+    pub max_iterations: u32,
+    pub system_prompt: String,
+}
+
+impl ReActAgent {
+    fn full_system_prompt(&self) -> String {
+        format!(
+            "{}\n\nYou have access to these tools:\n{}\n\n{}",
+            self.system_prompt,
+            self.tools.describe(),
+            FORMAT_INSTRUCTIONS
+        )
+    }
+
+    /// Runs the loop on `task` until the model gives a final answer or
+    /// `max_iterations` model calls have been made.
+    pub async fn run(&mut self, task: &str) -> Result<String> {
+        let system_prompt = self.full_system_prompt();
+        let mut messages = vec![Message::user(&format!("Task: {}\n\nThought:", task))];
+
+        for _ in 0..self.max_iterations {
+            let request = LLMRequest {
+                system_prompt: system_prompt.clone(),
+                messages: messages.clone(),
+                model: None,
+            };
+            let response = self.llm.invoke(&request).await?;
+
+            let observation = match parse_step(&response.content) {
+                Step::FinalAnswer(answer) => return Ok(answer),
+                Step::Action { tool, input } => match self.tools.call(&tool, &input).await {
+                    Ok(output) => output,
+                    Err(e) => format!("Error: {:#}", e),
+                },
+                Step::Invalid => "Your response did not contain an Action or a Final Answer. \
+                    Follow the required format."
+                    .to_string(),
+            };
+
+            messages.push(Message::assistant(&response.content));
+            messages.push(Message::user(&format!("Observation: {}\n\nThought:", observation)));
+        }
+
+        anyhow::bail!(
+            "ReAct agent did not reach a final answer within {} iterations",
+            self.max_iterations
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::test_support::{Scripted, Upper};
+
+    fn action(tool: &str, input: &str) -> Step {
+        Step::Action { tool: tool.to_string(), input: input.to_string() }
+    }
+
+    #[test]
+    fn parses_actions_and_final_answers() {
+        assert_eq!(parse_step("Thought: look it up\nAction: search\nAction Input: rust 2024"), action("search", "rust 2024"));
+        // A hallucinated observation, and anything after it, is ignored.
+        let hallucinated = "Action: search\nAction Input: rust\nObservation: made up\nFinal Answer: made up too";
+        assert_eq!(parse_step(hallucinated), action("search", "rust"));
+        assert_eq!(parse_step("Thought: no input needed\nAction: clock"), action("clock", ""));
+        // A final answer wins over an action in the same turn.
+        assert_eq!(parse_step("Action: search\nAction Input: x\nFinal Answer: 42"), Step::FinalAnswer("42".to_string()));
+        assert_eq!(parse_step("Action:\nAction Input: x"), Step::Invalid);
+        assert_eq!(parse_step("I think the answer is 42."), Step::Invalid);
+    }
+
+    #[tokio::test]
+    async fn calls_tools_until_the_final_answer() {
+        let llm = Scripted::new(&[
+            "Thought: I should shout it\nAction: upper\nAction Input: hello",
+            "Thought: I forgot the format",
+            "Thought: I now know the final answer\nFinal Answer: HELLO",
+        ]);
+        let mut tools = ToolRegistry::new();
+        tools.register(Box::new(Upper));
+        let mut agent = ReActAgent { llm: Box::new(llm.clone()), tools, max_iterations: 5, system_prompt: "Be loud.".to_string() };
+
+        assert_eq!(agent.run("shout hello").await.unwrap(), "HELLO");
+        let requests = llm.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[0].system_prompt.contains("upper: Upper-cases the input"), "{}", requests[0].system_prompt);
+        assert_eq!(requests[1].messages.last().unwrap().text_content(), "Observation: HELLO\n\nThought:");
+        assert!(requests[2].messages.last().unwrap().text_content().contains("did not contain an Action"));
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_iterations() {
+        let llm = Scripted::new(&["Action: missing\nAction Input: x", "Action: missing\nAction Input: y"]);
+        let mut agent = ReActAgent { llm: Box::new(llm.clone()), tools: ToolRegistry::new(), max_iterations: 2, system_prompt: String::new() };
+
+        let error = agent.run("anything").await.unwrap_err();
+        assert_eq!(error.to_string(), "ReAct agent did not reach a final answer within 2 iterations");
+        assert!(llm.requests()[1].messages.last().unwrap().text_content().starts_with("Observation: Error:"));
+    }
+}
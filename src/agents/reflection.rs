@@ -0,0 +1,135 @@
+//! Self-reflection: the model critiques its own draft and rewrites it.
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::llm::{LLMRequest, LLMResponse, Message, ModelInfo, LLM};
+
+const CRITIQUE_PROMPT: &str = "\
+Critique your previous response for accuracy, completeness, and clarity, then write an
+improved version. Use exactly this format:
+
+Critique: what is wrong or missing
+Improved Response: the full improved response";
+
+/// Wraps an `LLM` so every answer goes through `max_reflection_rounds` rounds
+/// of critique and rewrite. Token usage and latency cover every intermediate call.
+/// A prefilled assistant turn is merged into the first draft; the rewrites
+/// are complete answers, so they don't continue from the prefill.
+pub struct ReflectionAgent {
+    pub llm: Box<dyn LLM>,
+    pub max_reflection_rounds: u32,
+}
+
+#[async_trait]
+impl LLM for ReflectionAgent {
+    async fn invoke(&self, request: &LLMRequest) -> Result<LLMResponse> {
+        let mut response = self.llm.invoke(request).await?;
+        let mut input_tokens = response.input_tokens;
+        let mut output_tokens = response.output_tokens;
+        let mut latency_ms = response.latency_ms;
+        let mut cache_read_tokens = response.cache_read_tokens;
+
+        let mut history = request.messages.clone();
+        let mut draft = response.content.clone();
+        if history.last().is_some_and(|message| message.role == "assistant") {
+            let prefill = history.pop().expect("checked above").text_content();
+            draft = format!("{}{}", prefill, draft);
+        }
+
+        for _ in 0..self.max_reflection_rounds {
+            let mut messages = history.clone();
+            messages.push(Message::assistant(&draft));
+            messages.push(Message::user(CRITIQUE_PROMPT));
+            let reflection = self
+                .llm
+                .invoke(&LLMRequest {
+                    system_prompt: request.system_prompt.clone(),
+                    messages,
+                    model: request.model.clone(),
+                })
+                .await?;
+            input_tokens += reflection.input_tokens;
+            output_tokens += reflection.output_tokens;
+            latency_ms += reflection.latency_ms;
+            if let Some(cached) = reflection.cache_read_tokens {
+                cache_read_tokens = Some(cache_read_tokens.unwrap_or(0) + cached);
+            }
+
+            let improved = match reflection.content.split_once("Improved Response:") {
+                Some((_, improved)) => improved.trim().to_string(),
+                // The model ignored the format; its whole reply is the best rewrite we have.
+                None => reflection.content.trim().to_string(),
+            };
+            draft = improved.clone();
+            response = LLMResponse {
+                content: improved,
+                ..reflection
+            };
+        }
+
+        response.input_tokens = input_tokens;
+        response.output_tokens = output_tokens;
+        response.latency_ms = latency_ms;
+        response.cache_read_tokens = cache_read_tokens;
+        Ok(response)
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        self.llm.list_models().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::test_support::Scripted;
+
+    fn request(messages: Vec<Message>) -> LLMRequest {
+        LLMRequest { system_prompt: "Be accurate.".to_string(), messages, model: Some("claude-test".to_string()) }
+    }
+
+    #[tokio::test]
+    async fn usage_covers_every_call_and_the_last_rewrite_wins() {
+        let llm = Scripted::new(&[
+            "Paris is in Germany.",
+            "Critique: wrong country\nImproved Response: Paris is in France.",
+            "Critique: could name the region\nImproved Response:  Paris is in Île-de-France, France. ",
+        ]);
+        let agent = ReflectionAgent { llm: Box::new(llm.clone()), max_reflection_rounds: 2 };
+        let response = agent.invoke(&request(vec![Message::user("Where is Paris?")])).await.unwrap();
+
+        assert_eq!(response.content, "Paris is in Île-de-France, France.");
+        assert_eq!((response.input_tokens, response.output_tokens, response.latency_ms), (30, 15, 300));
+        assert_eq!(response.cache_read_tokens, Some(6));
+        // Each round critiques the latest draft, with the original settings.
+        let requests = llm.requests();
+        let critiqued: Vec<String> = requests[1..].iter().map(|r| r.messages[1].text_content()).collect();
+        assert_eq!(critiqued, ["Paris is in Germany.", "Paris is in France."]);
+        assert!(requests.iter().all(|r| r.system_prompt == "Be accurate." && r.model.as_deref() == Some("claude-test")));
+        assert_eq!(requests[1].messages[2].text_content(), CRITIQUE_PROMPT);
+    }
+
+    #[tokio::test]
+    async fn a_prefill_is_merged_into_the_first_draft() {
+        let llm = Scripted::new(&["\"Paris\"}", "Critique: name the country\nImproved Response: {\"city\": \"Paris\", \"country\": \"France\"}"]);
+        let agent = ReflectionAgent { llm: Box::new(llm.clone()), max_reflection_rounds: 1 };
+        let prefilled = request(vec![Message::user("Capital of France, as JSON?"), Message::assistant("{\"city\": ")]);
+        let response = agent.invoke(&prefilled).await.unwrap();
+
+        // The rewrite is a complete answer, not a continuation of the prefill.
+        assert_eq!(response.content, "{\"city\": \"Paris\", \"country\": \"France\"}");
+        let critique = &llm.requests()[1].messages;
+        assert_eq!(critique.len(), 3);
+        assert_eq!(critique[1].role, "assistant");
+        assert_eq!(critique[1].text_content(), "{\"city\": \"Paris\"}");
+    }
+
+    #[tokio::test]
+    async fn a_reply_without_the_format_is_the_rewrite() {
+        let llm = Scripted::new(&["Draft.", "  Just a better answer.  "]);
+        let agent = ReflectionAgent { llm: Box::new(llm), max_reflection_rounds: 1 };
+        let response = agent.invoke(&request(vec![Message::user("Answer?")])).await.unwrap();
+        assert_eq!(response.content, "Just a better answer.");
+    }
+}
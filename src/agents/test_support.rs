@@ -0,0 +1,67 @@
+//! A scripted model and a simple tool for the agent tests.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::llm::{LLMRequest, LLMResponse, LLM};
+use crate::tools::{Tool, ToolDefinition};
+
+/// Answers with the given replies in order, keeping every request it gets.
+/// Each reply costs 10 input and 5 output tokens, 2 of them cached, and 100ms.
+/// Clones share the script, so a test can keep one to inspect the requests.
+#[derive(Clone, Default)]
+pub(super) struct Scripted {
+    replies: Arc<Mutex<VecDeque<String>>>,
+    requests: Arc<Mutex<Vec<LLMRequest>>>,
+}
+
+impl Scripted {
+    pub(super) fn new(replies: &[&str]) -> Self {
+        let replies = replies.iter().map(|reply| reply.to_string()).collect();
+        Self { replies: Arc::new(Mutex::new(replies)), ..Self::default() }
+    }
+
+    pub(super) fn requests(&self) -> Vec<LLMRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl LLM for Scripted {
+    async fn invoke(&self, request: &LLMRequest) -> Result<LLMResponse> {
+        self.requests.lock().unwrap().push(request.clone());
+        let Some(content) = self.replies.lock().unwrap().pop_front() else {
+            anyhow::bail!("the script has no more replies");
+        };
+        Ok(LLMResponse {
+            content,
+            input_tokens: 10,
+            output_tokens: 5,
+            stop_reason: "end_turn".to_string(),
+            stop_sequence: None,
+            latency_ms: 100,
+            cache_read_tokens: Some(2),
+            truncated: false,
+        })
+    }
+}
+
+/// Upper-cases its input.
+pub(super) struct Upper;
+
+#[async_trait]
+impl Tool for Upper {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "upper".to_string(),
+            description: "Upper-cases the input".to_string(),
+            input_schema: serde_json::json!({ "type": "string" }),
+        }
+    }
+
+    async fn call(&self, input: &str) -> Result<String> {
+        Ok(input.to_uppercase())
+    }
+}
@@ -0,0 +1,138 @@
+//! Local files attached to a prompt as fenced code blocks.
+
+use anyhow::{Context, Result};
+use std::path::{Component, Path, PathBuf};
+
+/// Wraps `text` in a Markdown code fence longer than any backtick run inside it,
+/// with `info` (e.g. a language or path) after the opening fence.
+pub fn fenced(text: &str, info: &str) -> String {
+    let longest_run = text
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    format!("{}{}\n{}\n{}", fence, info, text.trim_end(), fence)
+}
+
+/// Reads a text file and renders it as a fenced block headed by its path.
+/// Files that aren't valid UTF-8 or contain NUL bytes are rejected as binary.
+pub fn load_text_file(path: &Path) -> Result<String> {
+    let bytes = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    if bytes.contains(&0) {
+        anyhow::bail!("{} looks like a binary file; only text files can be attached", path.display());
+    }
+    let text = String::from_utf8(bytes)
+        .map_err(|_| anyhow::anyhow!("{} is not valid UTF-8 text; only text files can be attached", path.display()))?;
+    let language = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+    Ok(format!("File: {}\n{}", path.display(), fenced(&text, language)))
+}
+
+/// Expands `*` and `?` wildcards in any component of `pattern`, returning the
+/// matching files sorted. A pattern without wildcards is returned as is.
+pub fn expand_glob(pattern: &str) -> Result<Vec<PathBuf>> {
+    if !pattern.contains(['*', '?']) {
+        return Ok(vec![PathBuf::from(pattern)]);
+    }
+    let mut candidates = vec![PathBuf::new()];
+    for component in Path::new(pattern).components() {
+        let part = component.as_os_str().to_string_lossy();
+        let has_wildcard = matches!(component, Component::Normal(_)) && part.contains(['*', '?']);
+        if !has_wildcard {
+            candidates.iter_mut().for_each(|path| path.push(component));
+            continue;
+        }
+        let mut next = Vec::new();
+        for dir in &candidates {
+            let listing = if dir.as_os_str().is_empty() { Path::new(".") } else { dir.as_path() };
+            let Ok(entries) = std::fs::read_dir(listing) else { continue };
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                // Like a shell, `*` doesn't match hidden files unless the pattern starts with `.`.
+                if name.starts_with('.') && !part.starts_with('.') {
+                    continue;
+                }
+                if wildcard_match(&part, &name) {
+                    next.push(dir.join(name));
+                }
+            }
+        }
+        candidates = next;
+    }
+    let mut matches: Vec<PathBuf> = candidates.into_iter().filter(|path| path.is_file()).collect();
+    if matches.is_empty() {
+        anyhow::bail!("No files match {}", pattern);
+    }
+    matches.sort();
+    Ok(matches)
+}
+
+/// Matches `name` against a pattern where `*` is any run of characters and `?` is one.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards_match_runs_and_single_characters() {
+        assert!(wildcard_match("*.rs", "main.rs"));
+        assert!(wildcard_match("*.rs", ".rs"));
+        assert!(!wildcard_match("*.rs", "main.rsx"));
+        assert!(wildcard_match("a?c", "abc"));
+        assert!(!wildcard_match("a?c", "ac"));
+        assert!(wildcard_match("*a*b", "xaxxab"));
+        assert!(!wildcard_match("*a*b", "xaxxa"));
+        assert!(wildcard_match("**", ""));
+        assert!(wildcard_match("données-?.txt", "données-1.txt"));
+    }
+
+    #[test]
+    fn globs_expand_in_any_component_sorted_and_skip_hidden_files() {
+        let root = std::env::temp_dir().join(format!("ra1-glob-{}", std::process::id()));
+        for file in ["src/b.rs", "src/a.rs", "src/.hidden.rs", "src/notes.md", "tests/c.rs"] {
+            std::fs::create_dir_all(root.join(file).parent().unwrap()).unwrap();
+            std::fs::write(root.join(file), "").unwrap();
+        }
+        let glob = |pattern: &str| expand_glob(&root.join(pattern).to_string_lossy());
+
+        assert_eq!(glob("src/*.rs").unwrap(), [root.join("src/a.rs"), root.join("src/b.rs")]);
+        assert_eq!(glob("*/?.rs").unwrap(), [root.join("src/a.rs"), root.join("src/b.rs"), root.join("tests/c.rs")]);
+        assert_eq!(glob("src/.*.rs").unwrap(), [root.join("src/.hidden.rs")]);
+        assert!(glob("src/*.py").unwrap_err().to_string().contains("No files match"));
+        // Directories match the wildcard but aren't returned.
+        assert!(glob("*").is_err());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn paths_without_wildcards_are_kept_as_given() {
+        assert_eq!(expand_glob("missing/file.txt").unwrap(), [PathBuf::from("missing/file.txt")]);
+    }
+}
@@ -0,0 +1,186 @@
+//! Running many independent requests with bounded concurrency.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::llm::{LLMRequest, LLMResponse, Message, LLM};
+
+/// A safe default that stays well inside typical per-key rate limits.
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Invokes every request with at most `concurrency` in flight at once.
+/// Results are returned in the same order as `requests`.
+pub async fn invoke_many(
+    llm: Arc<dyn LLM>,
+    requests: Vec<LLMRequest>,
+    concurrency: usize,
+) -> Vec<Result<LLMResponse>> {
+    let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut tasks = JoinSet::new();
+    for (index, request) in requests.into_iter().enumerate() {
+        let llm = Arc::clone(&llm);
+        let semaphore = Arc::clone(&semaphore);
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await.expect("semaphore closed");
+            (index, llm.invoke(&request).await)
+        });
+    }
+
+    let mut results: Vec<Option<Result<LLMResponse>>> = Vec::new();
+    results.resize_with(tasks.len(), || None);
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok((index, result)) => results[index] = Some(result),
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+    results
+        .into_iter()
+        .map(|result| result.expect("every task reports a result"))
+        .collect()
+}
+
+/// One line of a batch input file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchItem {
+    /// Caller-chosen identifier echoed in the output.
+    pub id: String,
+    pub prompt: String,
+    #[serde(default)]
+    pub system: Option<String>,
+}
+
+impl BatchItem {
+    pub fn to_request(&self, default_system_prompt: &str) -> LLMRequest {
+        LLMRequest {
+            system_prompt: self
+                .system
+                .clone()
+                .unwrap_or_else(|| default_system_prompt.to_string()),
+            messages: vec![Message::user(&self.prompt)],
+            model: None,
+        }
+    }
+}
+
+/// Reads a JSONL file of `BatchItem`s, skipping blank lines.
+pub fn read_batch_file(path: &Path) -> Result<Vec<BatchItem>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read batch file {}", path.display()))?;
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("Invalid batch item on line {} of {}", i + 1, path.display()))
+        })
+        .collect()
+}
+
+/// One line of a batch output file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchResult {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl BatchResult {
+    pub fn new(id: String, result: Result<LLMResponse>) -> Self {
+        match result {
+            Ok(response) => Self {
+                id,
+                content: Some(response.content),
+                input_tokens: response.input_tokens,
+                output_tokens: response.output_tokens,
+                error: None,
+            },
+            Err(e) => Self {
+                id,
+                content: None,
+                input_tokens: 0,
+                output_tokens: 0,
+                error: Some(format!("{:#}", e)),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    /// Echoes each prompt after a delay that varies with it, tracking the most
+    /// calls in flight at once. A prompt of "fail" fails.
+    #[derive(Default)]
+    struct Sleeper {
+        in_flight: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl LLM for Sleeper {
+        async fn invoke(&self, request: &LLMRequest) -> Result<LLMResponse> {
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+            let prompt = request.messages[0].text_content();
+            tokio::time::sleep(Duration::from_millis(5 + (prompt.len() as u64 * 37) % 40)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            if prompt == "fail" {
+                anyhow::bail!("failed on purpose");
+            }
+            Ok(LLMResponse {
+                content: prompt,
+                input_tokens: 1,
+                output_tokens: 1,
+                stop_reason: "end_turn".to_string(),
+                stop_sequence: None,
+                latency_ms: 0,
+                cache_read_tokens: None,
+                truncated: false,
+            })
+        }
+    }
+
+    fn requests(prompts: &[&str]) -> Vec<LLMRequest> {
+        prompts
+            .iter()
+            .map(|prompt| BatchItem { id: String::new(), prompt: prompt.to_string(), system: None }.to_request(""))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn results_keep_request_order_within_the_concurrency_limit() {
+        let prompts = ["a", "bbbbbbb", "cc", "fail", "dddd", "e", "ffffffffff", "ggg", "hhhhh", "i"];
+        let llm = Arc::new(Sleeper::default());
+        let results = invoke_many(llm.clone(), requests(&prompts), 3).await;
+
+        assert_eq!(llm.peak.load(Ordering::SeqCst), 3);
+        for (prompt, result) in prompts.iter().zip(&results) {
+            match *prompt {
+                "fail" => assert_eq!(result.as_ref().unwrap_err().to_string(), "failed on purpose"),
+                _ => assert_eq!(result.as_ref().unwrap().content, *prompt),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn zero_concurrency_runs_one_at_a_time() {
+        let llm = Arc::new(Sleeper::default());
+        let results = invoke_many(llm.clone(), requests(&["a", "bb", "ccc"]), 0).await;
+        assert_eq!(llm.peak.load(Ordering::SeqCst), 1);
+        assert_eq!(results.len(), 3);
+    }
+}
@@ -0,0 +1,193 @@
+//! VCR-style cassettes: record real request/response pairs to a JSONL file
+//! once, then replay them deterministically without calling the API.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::llm::{LLMRequest, LLMResponse, ModelInfo, LLM};
+
+/// One line of a cassette file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: LLMRequest,
+    pub response: LLMResponse,
+}
+
+/// Wraps an `LLM` and appends every successful interaction to a cassette.
+pub struct RecordingProvider {
+    inner: Box<dyn LLM>,
+    file: Mutex<File>,
+}
+
+impl RecordingProvider {
+    /// Opens `path` for appending, so repeated runs extend the same cassette.
+    pub fn new(inner: Box<dyn LLM>, path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open cassette {}", path.display()))?;
+        Ok(Self {
+            inner,
+            file: Mutex::new(file),
+        })
+    }
+}
+
+#[async_trait]
+impl LLM for RecordingProvider {
+    async fn invoke(&self, request: &LLMRequest) -> Result<LLMResponse> {
+        let response = self.inner.invoke(request).await?;
+        let line = serde_json::to_string(&Interaction {
+            request: request.clone(),
+            response: response.clone(),
+        })?;
+        let mut file = self.file.lock().expect("cassette lock poisoned");
+        writeln!(file, "{}", line).context("Failed to write to cassette")?;
+        Ok(response)
+    }
+
+    /// Passed through unrecorded; cassettes hold only `invoke` calls.
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        self.inner.list_models().await
+    }
+}
+
+/// Serves responses from a cassette, matching requests on system prompt and
+/// messages; a per-request model override doesn't affect the match. Identical
+/// requests are answered in recorded order; once those run out, the last
+/// matching response is repeated.
+pub struct ReplayProvider {
+    path: PathBuf,
+    interactions: Vec<Interaction>,
+    used: Mutex<Vec<bool>>,
+}
+
+impl ReplayProvider {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read cassette {}", path.display()))?;
+        let interactions = contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line)
+                    .with_context(|| format!("Invalid interaction on line {} of {}", i + 1, path.display()))
+            })
+            .collect::<Result<Vec<Interaction>>>()?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            used: Mutex::new(vec![false; interactions.len()]),
+            interactions,
+        })
+    }
+}
+
+#[async_trait]
+impl LLM for ReplayProvider {
+    async fn invoke(&self, request: &LLMRequest) -> Result<LLMResponse> {
+        let matches: Vec<usize> = self
+            .interactions
+            .iter()
+            .enumerate()
+            .filter(|(_, interaction)| {
+                interaction.request.system_prompt == request.system_prompt && interaction.request.messages == request.messages
+            })
+            .map(|(i, _)| i)
+            .collect();
+        let Some(&last) = matches.last() else {
+            let last_message = request
+                .messages
+                .last()
+                .map(|message| message.text_content())
+                .unwrap_or_default();
+            anyhow::bail!(
+                "No recorded response in cassette {} matches this request (last message: {:?})",
+                self.path.display(),
+                last_message
+            );
+        };
+
+        let mut used = self.used.lock().expect("cassette lock poisoned");
+        let index = matches.into_iter().find(|&i| !used[i]).unwrap_or(last);
+        used[index] = true;
+        Ok(self.interactions[index].response.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::Message;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Answers "reply 1", "reply 2", ... in call order.
+    #[derive(Default)]
+    struct Counter(AtomicUsize);
+
+    #[async_trait]
+    impl LLM for Counter {
+        async fn invoke(&self, _request: &LLMRequest) -> Result<LLMResponse> {
+            let n = self.0.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(LLMResponse {
+                content: format!("reply {}", n),
+                input_tokens: 10,
+                output_tokens: n as u32,
+                stop_reason: "end_turn".to_string(),
+                stop_sequence: None,
+                latency_ms: 0,
+                cache_read_tokens: None,
+                truncated: false,
+            })
+        }
+    }
+
+    fn request(text: &str) -> LLMRequest {
+        LLMRequest { system_prompt: "Be brief.".to_string(), messages: vec![Message::user(text)], model: None }
+    }
+
+    async fn content(llm: &dyn LLM, request: &LLMRequest) -> String {
+        llm.invoke(request).await.unwrap().content
+    }
+
+    #[tokio::test]
+    async fn replays_what_was_recorded_in_order() {
+        let cassette = tempfile::NamedTempFile::new().unwrap();
+        let recorder = RecordingProvider::new(Box::new(Counter::default()), cassette.path()).unwrap();
+        for text in ["ping", "ping", "other"] {
+            recorder.invoke(&request(text)).await.unwrap();
+        }
+
+        let replay = ReplayProvider::from_file(cassette.path()).unwrap();
+        assert_eq!(content(&replay, &request("ping")).await, "reply 1");
+        assert_eq!(content(&replay, &request("other")).await, "reply 3");
+        // A model override doesn't change which recording answers.
+        let other_model = LLMRequest { model: Some("claude-3-haiku-20240307".to_string()), ..request("ping") };
+        assert_eq!(content(&replay, &other_model).await, "reply 2");
+        // Once the recordings for a request are used up, the last one repeats.
+        assert_eq!(content(&replay, &request("ping")).await, "reply 2");
+        assert_eq!(content(&replay, &request("other")).await, "reply 3");
+    }
+
+    #[tokio::test]
+    async fn an_unrecorded_request_is_an_error() {
+        let cassette = tempfile::NamedTempFile::new().unwrap();
+        let recorder = RecordingProvider::new(Box::new(Counter::default()), cassette.path()).unwrap();
+        recorder.invoke(&request("ping")).await.unwrap();
+
+        let replay = ReplayProvider::from_file(cassette.path()).unwrap();
+        let other_system = LLMRequest { system_prompt: "Be thorough.".to_string(), ..request("ping") };
+        for unrecorded in [request("pong"), other_system] {
+            let error = replay.invoke(&unrecorded).await.unwrap_err().to_string();
+            assert!(error.starts_with("No recorded response in cassette"), "{}", error);
+        }
+        let error = replay.invoke(&request("pong")).await.unwrap_err().to_string();
+        assert!(error.ends_with(r#"(last message: "pong")"#), "{}", error);
+    }
+}
@@ -0,0 +1,46 @@
+//! Fenced code blocks pulled out of Markdown replies.
+
+/// The contents of every fenced code block in `text`, in order. A fence is a
+/// line starting with three or more backticks or tildes; it closes at a line
+/// of at least as many of the same character. An unclosed block runs to the end.
+pub fn extract_code_blocks(text: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut open: Option<(char, usize, String)> = None;
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        let fence_char = trimmed.chars().next().filter(|c| *c == '`' || *c == '~');
+        let fence_len = fence_char.map_or(0, |c| trimmed.chars().take_while(|&x| x == c).count());
+        match &mut open {
+            Some((c, len, body)) => {
+                if fence_char == Some(*c) && fence_len >= *len && trimmed[fence_len..].trim().is_empty() {
+                    blocks.push(std::mem::take(body));
+                    open = None;
+                } else {
+                    body.push_str(line);
+                    body.push('\n');
+                }
+            }
+            None if fence_len >= 3 => open = Some((fence_char.unwrap_or('`'), fence_len, String::new())),
+            None => {}
+        }
+    }
+    if let Some((_, _, body)) = open {
+        blocks.push(body);
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_each_block_without_its_fences() {
+        let reply = "Here you go:\n\n```rust\nfn main() {}\n```\n\nAnd a nested one:\n\n````md\n```\ninner\n```\n````\n\n~~~\nunclosed";
+        assert_eq!(
+            extract_code_blocks(reply),
+            vec!["fn main() {}\n".to_string(), "```\ninner\n```\n".to_string(), "unclosed\n".to_string()]
+        );
+        assert!(extract_code_blocks("No code here.").is_empty());
+    }
+}
@@ -0,0 +1,106 @@
+//! Shell completion, driven at Tab time by `clap_complete`'s dynamic engine.
+//!
+//! The script `completions <shell>` prints only registers the binary as its
+//! own completer: each Tab runs it with `COMPLETE=<shell>` set, and it answers
+//! from the current clap command tree. Values for the flags in `DYNAMIC_FLAGS`
+//! are read from the config at that moment, so new profiles and personas are
+//! offered without regenerating anything.
+
+use clap::Command;
+use clap_complete::env::Shells;
+use clap_complete::{ArgValueCandidates, CompletionCandidate};
+
+pub use clap_complete::Shell;
+
+/// Environment variable that switches the binary into completion mode.
+pub const COMPLETE_ENV: &str = "COMPLETE";
+
+/// Flags whose values are completed from the config, as (arg id, kind).
+pub const DYNAMIC_FLAGS: &[(&str, &str)] = &[("model", "models"), ("profile", "profiles"), ("persona", "personas")];
+
+/// `cmd` with the flags in `DYNAMIC_FLAGS` completed from `candidates(kind)`,
+/// which is only called when that flag's value is being completed.
+pub fn with_dynamic_values(mut cmd: Command, candidates: fn(&str) -> Vec<String>) -> Command {
+    for (id, kind) in DYNAMIC_FLAGS {
+        if cmd.get_arguments().any(|arg| arg.get_id() == id) {
+            let values = ArgValueCandidates::new(move || candidates(kind).into_iter().map(CompletionCandidate::new).collect());
+            cmd = cmd.mut_arg(*id, |arg| arg.add(values));
+        }
+    }
+    cmd
+}
+
+/// The script that registers `completer` (the path to this binary) as the
+/// completer for `name` in `shell`.
+pub fn registration(shell: Shell, name: &str, completer: &str) -> String {
+    let shells = Shells::builtins();
+    let env_shell = shells
+        .completer(&shell.to_string())
+        .expect("every clap_complete shell has a dynamic completer");
+    let mut script = Vec::new();
+    env_shell
+        .write_registration(COMPLETE_ENV, name, name, completer, &mut script)
+        .expect("writing to a Vec can't fail");
+    String::from_utf8(script).expect("completion scripts are UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Arg;
+    use std::ffi::OsString;
+
+    fn command() -> Command {
+        Command::new("claude-agent")
+            .arg(Arg::new("model").long("model").global(true))
+            .arg(Arg::new("persona").long("persona"))
+            .subcommand(Command::new("chat").arg(Arg::new("save").long("save").num_args(0)))
+    }
+
+    fn candidates(kind: &str) -> Vec<String> {
+        match kind {
+            "models" => vec!["claude-test-1".to_string(), "claude-test-2".to_string()],
+            _ => Vec::new(),
+        }
+    }
+
+    /// What Tab offers after `line`, the way the registered script asks for it.
+    fn complete(line: &[&str]) -> Vec<String> {
+        let mut cmd = with_dynamic_values(command(), candidates);
+        let args: Vec<OsString> = line.iter().map(OsString::from).collect();
+        clap_complete::engine::complete(&mut cmd, args, line.len() - 1, None)
+            .unwrap()
+            .iter()
+            .map(|candidate| candidate.get_value().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn every_shell_calls_back_into_the_binary() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish, Shell::PowerShell, Shell::Elvish] {
+            let script = registration(shell, "claude-agent", "/usr/local/bin/ra1");
+            assert!(script.contains("/usr/local/bin/ra1"), "{shell}: {script}");
+            assert!(script.contains(COMPLETE_ENV), "{shell}: {script}");
+        }
+    }
+
+    #[test]
+    fn zsh_is_native() {
+        let script = registration(Shell::Zsh, "claude-agent", "ra1");
+        assert!(script.starts_with("#compdef claude-agent"));
+        assert!(!script.contains("bashcompinit"));
+    }
+
+    #[test]
+    fn subcommands_and_flags_are_completed() {
+        assert!(complete(&["claude-agent", ""]).contains(&"chat".to_string()));
+        assert!(complete(&["claude-agent", "chat", "--s"]).contains(&"--save".to_string()));
+    }
+
+    #[test]
+    fn config_values_are_read_at_tab_time() {
+        assert_eq!(complete(&["claude-agent", "--model", "claude-test-"]), ["claude-test-1", "claude-test-2"]);
+        assert_eq!(complete(&["claude-agent", "chat", "--model", "claude-test-2"]), ["claude-test-2"]);
+        assert!(complete(&["claude-agent", "--persona", ""]).is_empty());
+    }
+}
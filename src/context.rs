@@ -0,0 +1,275 @@
+//! Keeping long conversations under a token budget by compacting their
+//! oldest messages, either dropping them or replacing them with a summary,
+//! and undoing the latest exchanges.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::llm::{LLMRequest, LLMResponse, Message, LLM};
+use crate::tokens::TokenEstimator;
+
+/// System prompt for the secondary call that writes the summary.
+pub const SUMMARY_SYSTEM_PROMPT: &str = "You write concise, faithful summaries of conversations.";
+
+/// Opens the user message that asks for the summary; the transcript follows it.
+pub const SUMMARY_INSTRUCTION: &str = "Summarize the following conversation between a user and an AI assistant. \
+Keep every fact, decision, name, and open question needed to continue it; leave out pleasantries.";
+
+/// Opens the user message that replaces summarized messages.
+const SUMMARY_HEADING: &str = "Summary of the earlier conversation:";
+
+/// How to shrink a conversation whose estimated input exceeds the limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContextStrategy {
+    /// Drop the oldest messages.
+    #[default]
+    Truncate,
+    /// Replace the oldest messages with a model-written summary, at the cost of one extra request.
+    Summarize,
+}
+
+/// What one compaction did.
+#[derive(Debug)]
+pub struct Compaction {
+    /// Messages dropped or folded into the summary.
+    pub removed: usize,
+    /// The summarization call, for `ContextStrategy::Summarize`.
+    pub summary: Option<LLMResponse>,
+}
+
+/// Where to cut `messages` so that at most `count` of the oldest go and the
+/// roles still alternate afterwards. Truncation must leave a user message
+/// first; summarizing must leave an assistant message first, since the
+/// summary itself becomes the leading user message. The last message (the
+/// turn about to be sent) is never removed. Returns 0 when nothing can go.
+fn cut_point(messages: &[Message], count: usize, strategy: ContextStrategy) -> usize {
+    let first_role = match strategy {
+        ContextStrategy::Truncate => "user",
+        ContextStrategy::Summarize => "assistant",
+    };
+    let mut cut = count.min(messages.len().saturating_sub(1));
+    while cut > 0 && messages[cut].role != first_role {
+        cut -= 1;
+    }
+    cut
+}
+
+/// Renders messages as a plain `User:`/`Assistant:` transcript for the summary request.
+fn transcript(messages: &[Message]) -> String {
+    messages
+        .iter()
+        .map(|message| {
+            let speaker = if message.role == "assistant" { "Assistant" } else { "User" };
+            format!("{}: {}", speaker, message.text_content())
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Compacts up to `count` of the oldest messages with `strategy`, using
+/// `llm` to write the summary. Returns `None` when nothing could be removed.
+pub async fn compact(
+    messages: &mut Vec<Message>,
+    strategy: ContextStrategy,
+    count: usize,
+    llm: &dyn LLM,
+) -> Result<Option<Compaction>> {
+    let cut = cut_point(messages, count, strategy);
+    if cut == 0 {
+        return Ok(None);
+    }
+    let summary = match strategy {
+        ContextStrategy::Truncate => {
+            messages.drain(..cut);
+            None
+        }
+        ContextStrategy::Summarize => {
+            let request = LLMRequest {
+                system_prompt: SUMMARY_SYSTEM_PROMPT.to_string(),
+                messages: vec![Message::user(&format!(
+                    "{}\n\n{}",
+                    SUMMARY_INSTRUCTION,
+                    transcript(&messages[..cut])
+                ))],
+                model: None,
+            };
+            let response = llm.invoke(&request).await.context("Failed to summarize earlier messages")?;
+            let summary = Message::user(&format!("{}\n\n{}", SUMMARY_HEADING, response.content));
+            messages.splice(..cut, [summary]);
+            Some(response)
+        }
+    };
+    Ok(Some(Compaction { removed: cut, summary }))
+}
+
+/// Estimated input tokens of the request built from `system_prompt` and `messages`.
+pub fn estimate(estimator: &dyn TokenEstimator, system_prompt: &str, messages: &[Message]) -> u32 {
+    let request = LLMRequest { system_prompt: system_prompt.to_string(), messages: messages.to_vec(), model: None };
+    estimator.estimate_request(&request)
+}
+
+// --- Exchanges ---
+
+/// `messages` without the summary a compaction leaves first, which isn't an
+/// exchange the user can count or undo.
+fn exchange_messages(messages: &[Message]) -> &[Message] {
+    match messages.first() {
+        Some(first) if first.role == "user" && first.text_content().starts_with(SUMMARY_HEADING) => &messages[1..],
+        _ => messages,
+    }
+}
+
+/// How many exchanges `messages` holds: user messages, each with the reply
+/// that follows it, if any.
+pub fn exchanges(messages: &[Message]) -> usize {
+    exchange_messages(messages).iter().filter(|message| message.role == "user").count()
+}
+
+/// Removes the last `count` exchanges from `messages` and returns them in
+/// order, or leaves `messages` alone and returns `None` if there are fewer.
+/// An unanswered user message at the end counts as an exchange.
+pub fn undo_exchanges(messages: &mut Vec<Message>, count: usize) -> Option<Vec<Message>> {
+    let first = messages.len() - exchange_messages(messages).len();
+    let mut start = messages.len();
+    for _ in 0..count {
+        start = first + messages[first..start].iter().rposition(|message| message.role == "user")?;
+    }
+    Some(messages.split_off(start))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// Replies with a fixed summary, or fails without one, keeping the requests it got.
+    struct Summarizer {
+        summary: Option<&'static str>,
+        requests: Mutex<Vec<LLMRequest>>,
+    }
+
+    impl Summarizer {
+        fn new(summary: Option<&'static str>) -> Self {
+            Self { summary, requests: Mutex::new(Vec::new()) }
+        }
+    }
+
+    #[async_trait]
+    impl LLM for Summarizer {
+        async fn invoke(&self, request: &LLMRequest) -> Result<LLMResponse> {
+            self.requests.lock().unwrap().push(request.clone());
+            let Some(summary) = self.summary else { anyhow::bail!("overloaded") };
+            Ok(LLMResponse {
+                content: summary.to_string(),
+                input_tokens: 40,
+                output_tokens: 8,
+                stop_reason: "end_turn".to_string(),
+                stop_sequence: None,
+                latency_ms: 0,
+                cache_read_tokens: None,
+                truncated: false,
+            })
+        }
+    }
+
+    fn conversation(turns: usize) -> Vec<Message> {
+        let mut messages = Vec::new();
+        for turn in 0..turns {
+            messages.push(Message::user(&format!("question {}", turn)));
+            messages.push(Message::assistant(&format!("answer {}", turn)));
+        }
+        messages.push(Message::user("latest"));
+        messages
+    }
+
+    #[test]
+    fn truncation_keeps_a_user_message_first() {
+        let messages = conversation(3);
+        assert_eq!(cut_point(&messages, 3, ContextStrategy::Truncate), 2);
+        assert_eq!(cut_point(&messages, 4, ContextStrategy::Truncate), 4);
+    }
+
+    #[test]
+    fn summarizing_keeps_an_assistant_message_first() {
+        let messages = conversation(3);
+        assert_eq!(cut_point(&messages, 4, ContextStrategy::Summarize), 3);
+        assert_eq!(cut_point(&messages, 1, ContextStrategy::Summarize), 1);
+    }
+
+    #[test]
+    fn the_pending_message_is_never_removed() {
+        let messages = conversation(1);
+        assert_eq!(cut_point(&messages, 10, ContextStrategy::Truncate), 2);
+        assert_eq!(cut_point(&[Message::user("only")], 10, ContextStrategy::Truncate), 0);
+    }
+
+    #[tokio::test]
+    async fn summarizing_replaces_the_oldest_messages_with_the_summary() {
+        let mut messages = conversation(3);
+        let llm = Summarizer::new(Some("They asked three questions."));
+        let compaction = compact(&mut messages, ContextStrategy::Summarize, 4, &llm).await.unwrap().unwrap();
+
+        assert_eq!(compaction.removed, 3);
+        assert_eq!(compaction.summary.unwrap().output_tokens, 8);
+        assert_eq!(messages.len(), 5);
+        assert_eq!(messages[0].text_content(), format!("{}\n\nThey asked three questions.", SUMMARY_HEADING));
+        assert_eq!(messages[1].text_content(), "answer 1");
+
+        let requests = llm.requests.lock().unwrap();
+        assert_eq!(requests[0].system_prompt, SUMMARY_SYSTEM_PROMPT);
+        let prompt = requests[0].messages[0].text_content();
+        assert!(prompt.starts_with(SUMMARY_INSTRUCTION), "{}", prompt);
+        assert!(prompt.ends_with("User: question 0\n\nAssistant: answer 0\n\nUser: question 1"), "{}", prompt);
+    }
+
+    #[tokio::test]
+    async fn a_failed_summary_leaves_messages_alone() {
+        let mut messages = conversation(3);
+        let error = compact(&mut messages, ContextStrategy::Summarize, 4, &Summarizer::new(None)).await.unwrap_err();
+        assert!(format!("{:#}", error).contains("overloaded"), "{:#}", error);
+        assert_eq!(messages, conversation(3));
+    }
+
+    #[tokio::test]
+    async fn truncating_makes_no_request() {
+        let mut messages = conversation(3);
+        let llm = Summarizer::new(None);
+        let compaction = compact(&mut messages, ContextStrategy::Truncate, 3, &llm).await.unwrap().unwrap();
+        assert_eq!((compaction.removed, compaction.summary.is_none()), (2, true));
+        assert_eq!(messages[0].text_content(), "question 1");
+        assert!(llm.requests.lock().unwrap().is_empty());
+        assert!(compact(&mut vec![Message::user("only")], ContextStrategy::Summarize, 4, &llm).await.unwrap().is_none());
+    }
+
+    #[test]
+    fn undo_removes_whole_exchanges() {
+        let mut messages = conversation(2);
+        assert_eq!(exchanges(&messages), 3);
+        let removed = undo_exchanges(&mut messages, 2).unwrap();
+        assert_eq!(removed.iter().map(Message::text_content).collect::<Vec<_>>(), ["question 1", "answer 1", "latest"]);
+        assert_eq!(messages.len(), 2);
+    }
+
+    #[test]
+    fn undo_leaves_messages_alone_when_there_are_too_few() {
+        let mut messages = conversation(1);
+        assert!(undo_exchanges(&mut messages, 3).is_none());
+        assert_eq!(messages.len(), 3);
+        assert!(undo_exchanges(&mut Vec::new(), 1).is_none());
+    }
+
+    #[test]
+    fn a_summary_is_not_an_exchange() {
+        let mut messages = vec![
+            Message::user(&format!("{}\n\nearlier", SUMMARY_HEADING)),
+            Message::assistant("answer 0"),
+            Message::user("question 1"),
+        ];
+        assert_eq!(exchanges(&messages), 1);
+        assert!(undo_exchanges(&mut messages, 2).is_none());
+        assert_eq!(undo_exchanges(&mut messages, 1).unwrap().len(), 1);
+        assert_eq!(exchanges(&messages), 0);
+    }
+}
@@ -0,0 +1,210 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tokio::fs;
+
+use crate::config::AgentConfig;
+use crate::providers::http;
+
+/// OpenAI's small embedding model, a good default for semantic search.
+pub const TEXT_EMBEDDING_3_SMALL: &str = "text-embedding-3-small";
+
+/// Output dimensions of `text-embedding-3-small`.
+pub const TEXT_EMBEDDING_3_SMALL_DIMENSIONS: usize = 1536;
+
+/// The most inputs OpenAI accepts in a single `/v1/embeddings` request.
+pub const MAX_INPUTS_PER_REQUEST: usize = 2048;
+
+// --- Core Abstraction ---
+
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    /// Embeds each text, returning one vector per input in the same order.
+    async fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>>;
+
+    /// The length of every vector returned by `embed`.
+    fn dimensions(&self) -> usize;
+
+    /// Convenience wrapper for embedding a single text.
+    async fn embed_one(&self, text: &str) -> Result<Vec<f32>> {
+        self.embed(&[text])
+            .await?
+            .pop()
+            .context("Embedding provider returned no vectors")
+    }
+}
+
+// --- Claude (placeholder) ---
+
+/// The Anthropic API has no embeddings endpoint; this type exists so code
+/// written against a "Claude" stack fails with a clear message instead of
+/// silently picking another vendor. Use `OpenAIEmbeddingProvider` instead.
+pub struct ClaudeEmbeddingProvider;
+
+#[async_trait]
+impl EmbeddingProvider for ClaudeEmbeddingProvider {
+    async fn embed(&self, _texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        anyhow::bail!(
+            "The Anthropic API does not provide embeddings; use OpenAIEmbeddingProvider instead"
+        )
+    }
+
+    fn dimensions(&self) -> usize {
+        0
+    }
+}
+
+// --- OpenAI ---
+
+#[derive(Serialize, Debug)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [&'a str],
+}
+
+#[derive(Deserialize, Debug)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize, Debug)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+/// Embeds text with OpenAI's `/v1/embeddings` endpoint.
+pub struct OpenAIEmbeddingProvider {
+    client: Client,
+    config: AgentConfig,
+    api_key: String,
+    api_base_url: String,
+    model: String,
+    dimensions: usize,
+}
+
+impl OpenAIEmbeddingProvider {
+    /// Creates a provider using `text-embedding-3-small` with the key stored at
+    /// `key_file_path`. Requests honor the configured timeout, proxy, and extra
+    /// root certificate.
+    pub async fn new(config: &AgentConfig, key_file_path: &Path) -> Result<Self> {
+        let api_key = fs::read_to_string(key_file_path)
+            .await
+            .with_context(|| format!("Failed to read API key from {}", key_file_path.display()))?;
+
+        Ok(Self {
+            client: http::build_client(config)?,
+            config: config.clone(),
+            api_key: api_key.trim().to_string(),
+            api_base_url: "https://api.openai.com".to_string(),
+            model: TEXT_EMBEDDING_3_SMALL.to_string(),
+            dimensions: TEXT_EMBEDDING_3_SMALL_DIMENSIONS,
+        })
+    }
+
+    /// Points the provider at an OpenAI-compatible server.
+    pub fn with_base_url(mut self, api_base_url: impl Into<String>) -> Self {
+        self.api_base_url = api_base_url.into();
+        self
+    }
+
+    /// Uses a different embedding model, which must produce `dimensions`-length vectors.
+    pub fn with_model(mut self, model: impl Into<String>, dimensions: usize) -> Self {
+        self.model = model.into();
+        self.dimensions = dimensions;
+        self
+    }
+
+    async fn embed_batch(&self, batch: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let response = self
+            .client
+            .post(format!("{}/v1/embeddings", self.api_base_url))
+            .bearer_auth(&self.api_key)
+            .json(&EmbeddingRequest {
+                model: &self.model,
+                input: batch,
+            })
+            .send()
+            .await
+            .map_err(|e| http::transport_error(&self.config, e, "Failed to send request to OpenAI embeddings API"))?;
+
+        let mut parsed: EmbeddingResponse = http::check_status(response)
+            .await?
+            .json()
+            .await
+            .context("Failed to parse embeddings response")?;
+
+        if parsed.data.len() != batch.len() {
+            anyhow::bail!(
+                "Embeddings API returned {} vectors for {} inputs",
+                parsed.data.len(),
+                batch.len()
+            );
+        }
+        // The API documents `index` rather than guaranteeing order.
+        parsed.data.sort_by_key(|d| d.index);
+        Ok(parsed.data.into_iter().map(|d| d.embedding).collect())
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OpenAIEmbeddingProvider {
+    async fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(MAX_INPUTS_PER_REQUEST) {
+            embeddings.extend(self.embed_batch(batch).await?);
+        }
+        Ok(embeddings)
+    }
+
+    fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::test_support::{key_file, mock_server};
+
+    /// A `/v1/embeddings` reply giving input `index` the vector `[value]`, in the order listed.
+    fn reply(vectors: impl Iterator<Item = (usize, f32)>) -> String {
+        let data: Vec<serde_json::Value> =
+            vectors.map(|(index, value)| serde_json::json!({ "index": index, "embedding": [value] })).collect();
+        serde_json::json!({ "data": data }).to_string()
+    }
+
+    async fn provider(api_base_url: String) -> OpenAIEmbeddingProvider {
+        let key_file = key_file("sk-embed\n");
+        let provider = OpenAIEmbeddingProvider::new(&AgentConfig::default(), key_file.path()).await.unwrap();
+        provider.with_base_url(api_base_url).with_model("test-embed", 1)
+    }
+
+    #[tokio::test]
+    async fn splits_large_inputs_and_orders_vectors_by_index() {
+        // The first batch comes back in reverse order; the second holds the one left over.
+        let first = reply((0..MAX_INPUTS_PER_REQUEST).rev().map(|index| (index, index as f32)));
+        let second = reply([(0, MAX_INPUTS_PER_REQUEST as f32)].into_iter());
+        let (url, server) = mock_server(vec![("200 OK", first), ("200 OK", second)]).await;
+
+        let texts: Vec<String> = (0..=MAX_INPUTS_PER_REQUEST).map(|i| format!("text {}", i)).collect();
+        let inputs: Vec<&str> = texts.iter().map(String::as_str).collect();
+        let embeddings = provider(url).await.embed(&inputs).await.unwrap();
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].contains("Bearer sk-embed") && requests[0].contains(r#""model":"test-embed""#), "{}", requests[0]);
+        assert!(requests[0].contains(r#""text 2047"]"#) && !requests[0].contains("text 2048"));
+        assert!(requests[1].contains(r#""input":["text 2048"]"#), "{}", requests[1]);
+        assert_eq!(embeddings.len(), MAX_INPUTS_PER_REQUEST + 1);
+        assert!(embeddings.iter().enumerate().all(|(i, vector)| vector == &[i as f32]));
+    }
+
+    #[tokio::test]
+    async fn a_missing_vector_is_an_error() {
+        let (url, _server) = mock_server(vec![("200 OK", reply([(0, 1.0)].into_iter()))]).await;
+        let error = provider(url).await.embed(&["one", "two"]).await.unwrap_err();
+        assert_eq!(error.to_string(), "Embeddings API returned 1 vectors for 2 inputs");
+    }
+}
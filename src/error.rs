@@ -0,0 +1,163 @@
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use std::fmt;
+use std::time::Duration;
+
+/// Structured failures returned by providers when the API rejects a request
+/// or doesn't answer in time.
+///
+/// Providers return these wrapped in `anyhow::Error`; callers that care about
+/// the specific case can `downcast_ref::<ApiError>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiError {
+    RateLimited { retry_after: Option<Duration> },
+    Unauthorized,
+    BadRequest(String),
+    ServerError(u16),
+    Overloaded,
+    /// The request exceeded the configured timeout.
+    Timeout(Duration),
+    /// A streamed response went longer than the configured idle timeout without sending anything.
+    StreamStalled(Duration),
+    /// The provider has no API for the named operation.
+    Unsupported(&'static str),
+}
+
+impl ApiError {
+    /// Classifies a non-success HTTP response.
+    pub fn from_response(status: StatusCode, headers: &HeaderMap, body: String) -> Self {
+        match status.as_u16() {
+            429 => ApiError::RateLimited {
+                retry_after: retry_after(headers),
+            },
+            401 | 403 => ApiError::Unauthorized,
+            // Anthropic signals an overloaded service with the non-standard 529.
+            529 => ApiError::Overloaded,
+            code @ 500..=599 => ApiError::ServerError(code),
+            _ => ApiError::BadRequest(body),
+        }
+    }
+}
+
+/// Parses a `retry-after` header given in whole seconds.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::RateLimited {
+                retry_after: Some(delay),
+            } => write!(f, "Rate limited, retry in {}s", delay.as_secs()),
+            ApiError::RateLimited { retry_after: None } => {
+                write!(f, "Rate limited, retry again shortly")
+            }
+            ApiError::Unauthorized => write!(f, "Unauthorized: check that your API key is valid"),
+            ApiError::BadRequest(body) => write!(f, "Bad request: {}", body),
+            ApiError::ServerError(code) => write!(f, "Server error (status {})", code),
+            ApiError::Overloaded => write!(f, "The API is overloaded, try again later"),
+            ApiError::Timeout(after) => write!(
+                f,
+                "Request timed out after {}s; raise request_timeout_secs (or pass --timeout) for long generations",
+                after.as_secs()
+            ),
+            ApiError::StreamStalled(after) => write!(
+                f,
+                "The response stream sent nothing for {}s; raise stream_idle_timeout_secs if the model pauses for longer",
+                after.as_secs()
+            ),
+            ApiError::Unsupported(operation) => write!(f, "This provider doesn't support {}", operation),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// Every problem found while validating an `AgentConfig`, reported together.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid configuration:")?;
+        for problem in &self.problems {
+            write!(f, "\n  - {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+// --- Exit Codes ---
+
+/// Any failure without a more specific code.
+pub const EXIT_FAILURE: i32 = 1;
+/// Invalid flags or configuration.
+pub const EXIT_USAGE_ERROR: i32 = 2;
+/// The API rejected the credentials (401/403).
+pub const EXIT_AUTH_ERROR: i32 = 3;
+/// The API rate limited the request (429).
+pub const EXIT_RATE_LIMITED: i32 = 4;
+/// The API couldn't be reached or didn't answer in time.
+pub const EXIT_NETWORK_ERROR: i32 = 5;
+
+/// The process exit status for `error`, from the first typed error in its
+/// chain: a `ConfigError`, an `ApiError`, or a connection-level `reqwest::Error`.
+pub fn exit_code(error: &anyhow::Error) -> i32 {
+    for cause in error.chain() {
+        if cause.is::<ConfigError>() {
+            return EXIT_USAGE_ERROR;
+        }
+        if let Some(api_error) = cause.downcast_ref::<ApiError>() {
+            return match api_error {
+                ApiError::Unauthorized => EXIT_AUTH_ERROR,
+                ApiError::RateLimited { .. } => EXIT_RATE_LIMITED,
+                ApiError::Timeout(_) | ApiError::StreamStalled(_) => EXIT_NETWORK_ERROR,
+                _ => EXIT_FAILURE,
+            };
+        }
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            if e.is_connect() || e.is_timeout() || e.is_request() {
+                return EXIT_NETWORK_ERROR;
+            }
+        }
+    }
+    EXIT_FAILURE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_codes_follow_the_error_class() {
+        let code = |e: ApiError| exit_code(&anyhow::Error::new(e).context("Failed to send request"));
+        assert_eq!(code(ApiError::Unauthorized), EXIT_AUTH_ERROR);
+        assert_eq!(code(ApiError::RateLimited { retry_after: None }), EXIT_RATE_LIMITED);
+        assert_eq!(code(ApiError::Timeout(Duration::from_secs(60))), EXIT_NETWORK_ERROR);
+        assert_eq!(code(ApiError::StreamStalled(Duration::from_secs(30))), EXIT_NETWORK_ERROR);
+        assert_eq!(code(ApiError::ServerError(500)), EXIT_FAILURE);
+        let config_error = ConfigError { problems: vec!["max_tokens must be at least 1".to_string()] };
+        assert_eq!(exit_code(&config_error.into()), EXIT_USAGE_ERROR);
+        assert_eq!(exit_code(&anyhow::anyhow!("something else")), EXIT_FAILURE);
+    }
+
+    #[tokio::test]
+    async fn refused_connections_are_network_errors() {
+        // Bind and drop a listener so the port is known to be closed.
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let e = reqwest::get(format!("http://127.0.0.1:{}", port)).await.unwrap_err();
+        assert_eq!(exit_code(&anyhow::Error::new(e).context("Failed to send request")), EXIT_NETWORK_ERROR);
+    }
+}
@@ -0,0 +1,329 @@
+//! Content filters that run on each request before it is sent and on each
+//! response before it is returned, for deployments that must keep certain
+//! content out of a conversation.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use regex::Regex;
+use std::fmt;
+
+use crate::llm::{LLMRequest, LLMResponse, ModelInfo, LLM};
+use crate::tokens::{CharHeuristicEstimator, TokenEstimator};
+
+/// `stop_reason` of a response a guardrail blocked or replaced.
+pub const GUARDRAIL_STOP_REASON: &str = "guardrail";
+
+/// How seriously a guardrail treats what it found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViolationSeverity {
+    /// Logged as a warning; the call goes ahead.
+    Warn,
+    /// The request isn't sent, or the response is withheld.
+    Block,
+}
+
+/// Why a guardrail objected to a request or response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuardrailViolation {
+    pub reason: String,
+    pub severity: ViolationSeverity,
+}
+
+impl fmt::Display for GuardrailViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.reason)
+    }
+}
+
+impl std::error::Error for GuardrailViolation {}
+
+/// A filter on what goes to and comes back from the model. Both checks
+/// default to allowing everything.
+pub trait Guardrail: Send + Sync {
+    fn check_input(&self, _request: &LLMRequest) -> Result<(), GuardrailViolation> {
+        Ok(())
+    }
+
+    fn check_output(&self, _response: &LLMResponse) -> Result<(), GuardrailViolation> {
+        Ok(())
+    }
+
+    /// Edits a response that passed every check before it's returned, for
+    /// guardrails that trim rather than reject. Does nothing by default.
+    fn limit_output(&self, _response: &mut LLMResponse) {}
+}
+
+/// Wraps an `LLM` and runs every guardrail on each call, in the order added.
+/// A blocked request is never sent: the call returns a response with no
+/// tokens whose content is the violation. A blocked response has its content
+/// replaced but keeps its token counts, since the call was still billed. Both
+/// are marked with [`GUARDRAIL_STOP_REASON`], so callers can leave the turn
+/// out of the history.
+pub struct GuardrailsMiddleware {
+    inner: Box<dyn LLM>,
+    guardrails: Vec<Box<dyn Guardrail>>,
+}
+
+impl GuardrailsMiddleware {
+    pub fn new(inner: Box<dyn LLM>) -> Self {
+        Self { inner, guardrails: Vec::new() }
+    }
+
+    /// Adds `guardrail` to the end of the list.
+    pub fn with_guardrail(mut self, guardrail: impl Guardrail + 'static) -> Self {
+        self.guardrails.push(Box::new(guardrail));
+        self
+    }
+
+    /// The first blocking violation among `checks`, after logging any warnings.
+    fn first_block(checks: impl Iterator<Item = Result<(), GuardrailViolation>>, stage: &str) -> Option<GuardrailViolation> {
+        for violation in checks.filter_map(Result::err) {
+            match violation.severity {
+                ViolationSeverity::Warn => tracing::warn!(stage, reason = violation.reason.as_str(), "Guardrail warning"),
+                ViolationSeverity::Block => return Some(violation),
+            }
+        }
+        None
+    }
+}
+
+#[async_trait]
+impl LLM for GuardrailsMiddleware {
+    async fn invoke(&self, request: &LLMRequest) -> Result<LLMResponse> {
+        let input_checks = self.guardrails.iter().map(|guardrail| guardrail.check_input(request));
+        if let Some(violation) = Self::first_block(input_checks, "input") {
+            return Ok(LLMResponse {
+                content: format!("Request blocked by guardrail: {}", violation.reason),
+                input_tokens: 0,
+                output_tokens: 0,
+                stop_reason: GUARDRAIL_STOP_REASON.to_string(),
+                stop_sequence: None,
+                latency_ms: 0,
+                cache_read_tokens: None,
+                truncated: false,
+            });
+        }
+        let mut response = self.inner.invoke(request).await?;
+        let output_checks = self.guardrails.iter().map(|guardrail| guardrail.check_output(&response));
+        if let Some(violation) = Self::first_block(output_checks, "output") {
+            response.content = format!("Response withheld by guardrail: {}", violation.reason);
+            response.stop_reason = GUARDRAIL_STOP_REASON.to_string();
+            response.stop_sequence = None;
+            return Ok(response);
+        }
+        for guardrail in &self.guardrails {
+            guardrail.limit_output(&mut response);
+        }
+        Ok(response)
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        self.inner.list_models().await
+    }
+}
+
+// --- Blocklist ---
+
+/// Rejects a request with any message, or a response whose text, matching
+/// any of `blocked_patterns`. Every message is checked, so blocked content
+/// can't reach the model from a loaded or edited history either.
+#[derive(Debug, Clone)]
+pub struct BlocklistGuardrail {
+    pub blocked_patterns: Vec<Regex>,
+    pub severity: ViolationSeverity,
+}
+
+impl BlocklistGuardrail {
+    /// Compiles `patterns`, which block by default.
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Result<Self, regex::Error> {
+        let blocked_patterns = patterns.iter().map(|pattern| Regex::new(pattern.as_ref())).collect::<Result<_, _>>()?;
+        Ok(Self { blocked_patterns, severity: ViolationSeverity::Block })
+    }
+
+    /// Reports matches with `severity` instead of blocking.
+    pub fn with_severity(mut self, severity: ViolationSeverity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    fn check(&self, text: &str, what: &str) -> Result<(), GuardrailViolation> {
+        match self.blocked_patterns.iter().find(|pattern| pattern.is_match(text)) {
+            Some(pattern) => Err(GuardrailViolation {
+                reason: format!("{} matches blocked pattern '{}'", what, pattern.as_str()),
+                severity: self.severity,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Guardrail for BlocklistGuardrail {
+    fn check_input(&self, request: &LLMRequest) -> Result<(), GuardrailViolation> {
+        request.messages.iter().try_for_each(|message| self.check(&message.text_content(), "a message"))
+    }
+
+    fn check_output(&self, response: &LLMResponse) -> Result<(), GuardrailViolation> {
+        self.check(&response.content, "the response")
+    }
+}
+
+// --- Max Length ---
+
+/// Marker appended to a response cut short by [`MaxLengthGuardrail`].
+pub const TRUNCATED_MARKER: &str = "[truncated]";
+
+/// Blocks a request whose newest message is longer than `max_input_chars`,
+/// or whose estimated input (system prompt plus history, as billed) exceeds
+/// `max_input_tokens`. A response that's too long isn't withheld: it's cut
+/// at a word boundary and marked [`TRUNCATED_MARKER`]. A response over
+/// `max_output_tokens` keeps the same share of its text as that limit is of
+/// its `output_tokens`. Every limit defaults to unlimited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaxLengthGuardrail {
+    pub max_input_chars: usize,
+    pub max_output_chars: usize,
+    pub max_input_tokens: u32,
+    pub max_output_tokens: u32,
+}
+
+impl Default for MaxLengthGuardrail {
+    fn default() -> Self {
+        Self { max_input_chars: usize::MAX, max_output_chars: usize::MAX, max_input_tokens: u32::MAX, max_output_tokens: u32::MAX }
+    }
+}
+
+impl MaxLengthGuardrail {
+    /// How many characters of `response` to keep, or `None` to keep them all.
+    fn output_limit(&self, response: &LLMResponse) -> Option<usize> {
+        let chars = response.content.chars().count();
+        let mut limit = self.max_output_chars;
+        if response.output_tokens > self.max_output_tokens {
+            let share = chars as u64 * u64::from(self.max_output_tokens) / u64::from(response.output_tokens);
+            limit = limit.min(share as usize);
+        }
+        (chars > limit).then_some(limit)
+    }
+}
+
+/// `text` cut to at most `limit` characters, backing up to the last
+/// whitespace so no word is split (unless the cut falls in the first word),
+/// then marked as truncated.
+fn truncate_at_word(text: &str, limit: usize) -> String {
+    let end = text.char_indices().nth(limit).map_or(text.len(), |(index, _)| index);
+    let kept = &text[..end];
+    let kept = match text[end..].starts_with(char::is_whitespace) {
+        true => kept,
+        false => kept.rfind(char::is_whitespace).map_or(kept, |index| &kept[..index]),
+    };
+    match kept.trim_end() {
+        "" => TRUNCATED_MARKER.to_string(),
+        kept => format!("{} {}", kept, TRUNCATED_MARKER),
+    }
+}
+
+impl Guardrail for MaxLengthGuardrail {
+    fn check_input(&self, request: &LLMRequest) -> Result<(), GuardrailViolation> {
+        let chars = request.messages.last().map_or(0, |message| message.text_content().chars().count());
+        if chars > self.max_input_chars {
+            return Err(GuardrailViolation {
+                reason: format!("the message is {} characters, over the limit of {}", chars, self.max_input_chars),
+                severity: ViolationSeverity::Block,
+            });
+        }
+        let tokens = CharHeuristicEstimator.estimate_request(request);
+        if tokens > self.max_input_tokens {
+            return Err(GuardrailViolation {
+                reason: format!("the request is about {} tokens, over the limit of {}", tokens, self.max_input_tokens),
+                severity: ViolationSeverity::Block,
+            });
+        }
+        Ok(())
+    }
+
+    fn limit_output(&self, response: &mut LLMResponse) {
+        if let Some(limit) = self.output_limit(response) {
+            tracing::debug!(limit, output_tokens = response.output_tokens, "Truncating long response");
+            response.content = truncate_at_word(&response.content, limit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::Message;
+    use crate::providers::dry_run::DryRunLLM;
+    use crate::AgentConfig;
+
+    fn request(text: &str) -> LLMRequest {
+        LLMRequest { system_prompt: String::new(), messages: vec![Message::user(text)], model: None }
+    }
+
+    #[tokio::test]
+    async fn blocked_input_is_answered_without_sending_it() {
+        let blocklist = BlocklistGuardrail::new(&[r"(?i)\bpassword\b", r"\d{3}-\d{2}-\d{4}"]).unwrap();
+        let llm = GuardrailsMiddleware::new(Box::new(DryRunLLM::new(AgentConfig::default()))).with_guardrail(blocklist);
+
+        // A dry run echoes what it's sent, so a sent request would show up here.
+        let blocked = llm.invoke(&request("My PASSWORD is hunter2")).await.unwrap();
+        assert_eq!(blocked.stop_reason, GUARDRAIL_STOP_REASON);
+        assert_eq!(blocked.content, r"Request blocked by guardrail: a message matches blocked pattern '(?i)\bpassword\b'");
+        assert_eq!((blocked.input_tokens, blocked.output_tokens), (0, 0));
+
+        let allowed = llm.invoke(&request("Passwords are hard")).await.unwrap();
+        assert_ne!(allowed.stop_reason, GUARDRAIL_STOP_REASON);
+
+        // Blocked content earlier in the history is caught too.
+        let mut history = request("Passwords are hard");
+        history.messages.insert(0, Message::user("123-45-6789"));
+        history.messages.insert(1, Message::assistant("Noted."));
+        assert_eq!(llm.invoke(&history).await.unwrap().stop_reason, GUARDRAIL_STOP_REASON);
+    }
+
+    /// Withholds every response that echoes a request, as a dry run's does.
+    struct NoEcho;
+
+    impl Guardrail for NoEcho {
+        fn check_output(&self, response: &LLMResponse) -> Result<(), GuardrailViolation> {
+            match response.content.contains("\"messages\"") {
+                true => Err(GuardrailViolation { reason: "echoed request".to_string(), severity: ViolationSeverity::Block }),
+                false => Ok(()),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn warnings_let_the_call_through_and_outputs_are_checked() {
+        let warn = BlocklistGuardrail::new(&["hello"]).unwrap().with_severity(ViolationSeverity::Warn);
+        let llm = GuardrailsMiddleware::new(Box::new(DryRunLLM::new(AgentConfig::default())))
+            .with_guardrail(warn)
+            .with_guardrail(NoEcho);
+
+        let withheld = llm.invoke(&request("hello")).await.unwrap();
+        assert_eq!(withheld.stop_reason, GUARDRAIL_STOP_REASON);
+        assert_eq!(withheld.content, "Response withheld by guardrail: echoed request");
+        assert!(withheld.input_tokens > 0);
+        assert!(BlocklistGuardrail::new(&["("]).is_err());
+    }
+
+    #[tokio::test]
+    async fn max_length_blocks_long_input_and_truncates_long_output() {
+        let limits = MaxLengthGuardrail { max_input_chars: 40, max_output_chars: 25, ..Default::default() };
+        let llm = GuardrailsMiddleware::new(Box::new(DryRunLLM::new(AgentConfig::default()))).with_guardrail(limits);
+
+        let blocked = llm.invoke(&request(&"word ".repeat(10))).await.unwrap();
+        assert_eq!(blocked.stop_reason, GUARDRAIL_STOP_REASON);
+        assert!(blocked.content.contains("50 characters"), "{}", blocked.content);
+
+        let truncated = llm.invoke(&request("hi")).await.unwrap();
+        assert_ne!(truncated.stop_reason, GUARDRAIL_STOP_REASON);
+        assert!(truncated.content.ends_with(" [truncated]"), "{}", truncated.content);
+        assert!(truncated.content.chars().count() <= 25 + " [truncated]".len());
+
+        assert_eq!(truncate_at_word("the quick brown fox", 12), "the quick [truncated]");
+        assert_eq!(truncate_at_word("the quick brown fox", 9), "the quick [truncated]");
+        assert_eq!(truncate_at_word("unbroken", 3), "unb [truncated]");
+        let tokens = MaxLengthGuardrail { max_input_tokens: 1, ..Default::default() };
+        assert!(tokens.check_input(&request("a longer message than one token")).is_err());
+    }
+}
@@ -0,0 +1,47 @@
+//! Loading local image files as message content.
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use std::path::Path;
+
+use crate::llm::{Content, ImageSource};
+
+/// Largest image the API accepts, before base64 encoding.
+pub const MAX_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+
+/// Media type for an image path, based on its extension.
+pub fn media_type_for(path: &Path) -> Result<&'static str> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    match extension.as_str() {
+        "png" => Ok("image/png"),
+        "jpg" | "jpeg" => Ok("image/jpeg"),
+        "gif" => Ok("image/gif"),
+        "webp" => Ok("image/webp"),
+        _ => anyhow::bail!(
+            "Unsupported image format for {} (expected png, jpeg, gif, or webp)",
+            path.display()
+        ),
+    }
+}
+
+/// Reads the image at `path` and base64-encodes it into a content part.
+pub fn load_image(path: &Path) -> Result<Content> {
+    let media_type = media_type_for(path)?;
+    let bytes = std::fs::read(path).with_context(|| format!("Failed to read image {}", path.display()))?;
+    if bytes.len() > MAX_IMAGE_BYTES {
+        anyhow::bail!(
+            "Image {} is {} bytes; the API accepts at most {} bytes per image",
+            path.display(),
+            bytes.len(),
+            MAX_IMAGE_BYTES
+        );
+    }
+    Ok(Content::Image {
+        source: ImageSource::Base64(base64::engine::general_purpose::STANDARD.encode(bytes)),
+        media_type: media_type.to_string(),
+    })
+}
@@ -0,0 +1,77 @@
+//! API key storage: the OS keyring (with the `keyring` feature) or a plain key file.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+use crate::paths;
+
+/// Keyring service name the API key is stored under.
+pub const KEYRING_SERVICE: &str = "claude-agent";
+/// Keyring user/account name the API key is stored under.
+pub const KEYRING_USER: &str = "anthropic-api-key";
+
+/// Whether this build can talk to the OS keyring.
+pub const fn keyring_supported() -> bool {
+    cfg!(feature = "keyring")
+}
+
+/// Reads the API key from the keyring. Any failure (no entry, no keyring
+/// daemon, unsupported build) yields `None` so callers fall back to the file.
+pub fn keyring_get() -> Option<String> {
+    #[cfg(feature = "keyring")]
+    {
+        keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)
+            .and_then(|entry| entry.get_password())
+            .ok()
+            .map(|key| key.trim().to_string())
+            .filter(|key| !key.is_empty())
+    }
+    #[cfg(not(feature = "keyring"))]
+    {
+        None
+    }
+}
+
+/// Stores the API key in the keyring.
+pub fn keyring_set(api_key: &str) -> Result<()> {
+    #[cfg(feature = "keyring")]
+    {
+        keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)
+            .and_then(|entry| entry.set_password(api_key.trim()))
+            .context("Failed to store API key in the OS keyring")
+    }
+    #[cfg(not(feature = "keyring"))]
+    {
+        let _ = api_key;
+        anyhow::bail!("This build has no keyring support; rebuild with `--features keyring`")
+    }
+}
+
+/// Reads the API key, preferring the keyring and falling back to `key_file_path`.
+pub async fn read_api_key(key_file_path: &Path) -> Result<String> {
+    match keyring_get() {
+        Some(key) => Ok(key),
+        None => read_key_file(key_file_path).await,
+    }
+}
+
+/// Reads one API key per file in `key_files`, or only the keyring's key when
+/// one is stored there.
+pub async fn read_api_keys(key_files: &[PathBuf]) -> Result<Vec<String>> {
+    if let Some(key) = keyring_get() {
+        return Ok(vec![key]);
+    }
+    let mut keys = Vec::with_capacity(key_files.len());
+    for key_file_path in key_files {
+        keys.push(read_key_file(key_file_path).await?);
+    }
+    Ok(keys)
+}
+
+async fn read_key_file(key_file_path: &Path) -> Result<String> {
+    let key_file_path = paths::resolve_key_file(key_file_path);
+    let api_key = tokio::fs::read_to_string(&key_file_path)
+        .await
+        .with_context(|| format!("Failed to read API key from {}", key_file_path.display()))?;
+    Ok(api_key.trim().to_string())
+}
@@ -0,0 +1,60 @@
+pub mod agents;
+pub mod attachments;
+pub mod batch;
+pub mod cassette;
+pub mod codeblocks;
+pub mod completions;
+pub mod config;
+pub mod context;
+pub mod embeddings;
+pub mod error;
+pub mod guardrails;
+pub mod image;
+pub mod keystore;
+pub mod llm;
+pub mod logging;
+pub mod middleware;
+pub mod models;
+pub mod paths;
+pub mod pricing;
+pub mod providers;
+pub mod rag;
+pub mod script;
+pub mod session;
+pub mod slash;
+pub mod stats;
+pub mod suggest;
+pub mod template;
+pub mod tokens;
+pub mod tools;
+pub mod transcript;
+pub mod usage;
+pub mod vector_store;
+
+pub use cassette::{RecordingProvider, ReplayProvider};
+pub use config::AgentConfig;
+pub use error::{ApiError, ConfigError};
+pub use guardrails::{BlocklistGuardrail, Guardrail, GuardrailViolation, GuardrailsMiddleware, MaxLengthGuardrail, ViolationSeverity};
+pub use llm::{validate_messages, Content, ImageSource, LLMRequest, LLMResponse, Message, ModelInfo, ResponseFormat, LLM};
+pub use middleware::{AuditLogger, CallCounter, Middleware, MiddlewareProvider, ModelOverride, RequestLogger};
+pub use pricing::{ModelPricing, PricingTable};
+pub use providers::azure_openai::{AzureAgentConfig, AzureOpenAIProvider};
+#[cfg(feature = "use-bedrock")]
+pub use providers::bedrock::{AwsBedrockProvider, BedrockAgentConfig};
+pub use providers::claude::ClaudeProvider;
+pub use providers::cohere::CohereProvider;
+pub use providers::dry_run::DryRunLLM;
+pub use providers::groq::GroqProvider;
+pub use providers::huggingface::HuggingFaceProvider;
+pub use providers::mistral::MistralProvider;
+pub use providers::openai_compat::OpenAICompatibleProvider;
+pub use providers::perplexity::{PerplexityProvider, PerplexityResponse};
+pub use providers::together::TogetherAIProvider;
+#[cfg(feature = "use-vertex")]
+pub use providers::vertex::{VertexAIProvider, VertexAgentConfig};
+pub use rag::{DocumentChunker, RagPipeline};
+pub use stats::SessionStats;
+pub use template::PromptTemplate;
+pub use tokens::{CharHeuristicEstimator, TokenEstimator};
+pub use tools::{Tool, ToolDefinition, ToolRegistry};
+pub use vector_store::VectorStore;
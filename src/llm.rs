@@ -0,0 +1,197 @@
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ApiError;
+
+// --- Core Abstraction (Our New Primitive) ---
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LLMRequest {
+    pub system_prompt: String,
+    pub messages: Vec<Message>,
+    /// Overrides the provider's configured model for this request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LLMResponse {
+    pub content: String,
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    /// Why generation stopped: `end_turn`, `max_tokens`, `stop_sequence`, or `tool_use`.
+    pub stop_reason: String,
+    /// The stop sequence that ended generation, when `stop_reason` is `stop_sequence`.
+    pub stop_sequence: Option<String>,
+    /// Time from sending the request to having the parsed response, in milliseconds.
+    #[serde(default)]
+    pub latency_ms: u64,
+    /// Input tokens served from the provider's prompt cache, for providers that report it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_tokens: Option<u32>,
+    /// The stream ended early (timeout or dropped connection), so `content` and
+    /// `output_tokens` cover only what arrived before it did.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+impl LLMResponse {
+    /// Parses the reply as JSON, e.g. one asked for with
+    /// [`ResponseFormat::JsonObject`]. A reply wrapped in a fenced code block
+    /// is parsed from inside the fence.
+    pub fn parse_json<T: DeserializeOwned>(&self) -> Result<T> {
+        let text = self.content.trim();
+        let fenced = (text.starts_with("```") || text.starts_with("~~~"))
+            .then(|| crate::codeblocks::extract_code_blocks(text).into_iter().next())
+            .flatten();
+        serde_json::from_str(fenced.as_deref().unwrap_or(text)).context("The response is not the expected JSON")
+    }
+}
+
+/// The shape the model is asked to reply in, from `response_format`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// Free-form text, the API default.
+    #[default]
+    Text,
+    /// Any JSON object.
+    JsonObject,
+    /// JSON matching this JSON Schema.
+    JsonSchema(serde_json::Value),
+}
+
+/// A model a provider can serve, as reported by its model listing endpoint.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ModelInfo {
+    pub id: String,
+    /// Human-readable name, for providers that report one.
+    pub display_name: Option<String>,
+    /// The most tokens (input plus output) one request can use, for providers that report it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_length: Option<u32>,
+}
+
+#[async_trait]
+pub trait LLM: Send + Sync {
+    /// The core function for any agent. It takes a request and returns a complete response.
+    async fn invoke(&self, request: &LLMRequest) -> Result<LLMResponse>;
+
+    /// The models available to this provider's credentials. Providers without
+    /// a listing endpoint return `ApiError::Unsupported`.
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        Err(ApiError::Unsupported("listing models").into())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Message {
+    pub role: String,
+    pub content: Vec<Content>,
+}
+
+/// One part of a message. Providers translate these into their own wire format.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Content {
+    Text(String),
+    Image { source: ImageSource, media_type: String },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageSource {
+    /// Base64-encoded image bytes.
+    Base64(String),
+    Url(String),
+}
+
+impl Message {
+    /// A message holding a single text part.
+    pub fn text(role: &str, content: &str) -> Self {
+        Self {
+            role: role.to_string(),
+            content: vec![Content::Text(content.to_string())],
+        }
+    }
+
+    pub fn user(content: &str) -> Self {
+        Self::text("user", content)
+    }
+
+    pub fn assistant(content: &str) -> Self {
+        Self::text("assistant", content)
+    }
+
+    /// The text parts of the message joined by newlines; images are skipped.
+    pub fn text_content(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(|part| match part {
+                Content::Text(text) => Some(text.as_str()),
+                Content::Image { .. } => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Checks that `messages` is a history the Messages API accepts: it starts
+/// with a `user` message and alternates `user` and `assistant` from there.
+/// Providers call this before sending, so a malformed history fails with a
+/// local error naming the message at fault instead of a remote 400.
+pub fn validate_messages(messages: &[Message]) -> Result<()> {
+    if messages.is_empty() {
+        bail!("Invalid conversation: there are no messages to send");
+    }
+    for (index, message) in messages.iter().enumerate() {
+        let expected = if index % 2 == 0 { "user" } else { "assistant" };
+        if message.role != expected {
+            bail!(
+                "Invalid conversation: message {} is from '{}' but should be from '{}' (messages must start with user and alternate with assistant)",
+                index + 1,
+                message.role,
+                expected
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn histories_must_start_with_user_and_alternate() {
+        assert!(validate_messages(&[Message::user("hi"), Message::assistant("hello"), Message::user("bye")]).is_ok());
+        // A trailing assistant message is a prefill.
+        assert!(validate_messages(&[Message::user("hi"), Message::assistant("{")]).is_ok());
+        assert!(validate_messages(&[]).is_err());
+        let doubled = validate_messages(&[Message::user("hi"), Message::user("again")]).unwrap_err();
+        assert!(doubled.to_string().contains("message 2 is from 'user'"), "{}", doubled);
+        assert!(validate_messages(&[Message::assistant("hello")]).is_err());
+    }
+
+    #[test]
+    fn parses_json_replies_fenced_or_not() {
+        let reply = |content: &str| LLMResponse {
+            content: content.to_string(),
+            input_tokens: 0,
+            output_tokens: 0,
+            stop_reason: "end_turn".to_string(),
+            stop_sequence: None,
+            latency_ms: 0,
+            cache_read_tokens: None,
+            truncated: false,
+        };
+        let parsed: HashMap<String, u32> = reply(" {\"answer\": 42}\n").parse_json().unwrap();
+        assert_eq!(parsed["answer"], 42);
+        let parsed: serde_json::Value = reply("```json\n{\"answer\": 42}\n```").parse_json().unwrap();
+        assert_eq!(parsed["answer"], 42);
+        assert!(reply("The answer is 42.").parse_json::<serde_json::Value>().is_err());
+    }
+}
@@ -0,0 +1,49 @@
+//! Diagnostics on stderr through `tracing-subscriber`'s fmt layer, filtered
+//! by `-v` count or by `RUST_LOG` directives (`debug`, `ra1::providers=trace`).
+
+use tracing::level_filters::LevelFilter;
+use tracing::Level;
+use tracing_subscriber::EnvFilter;
+
+/// The most verbose level shown for `-v` given `verbosity` times: warnings by
+/// default, per-call diagnostics at `-v`, request bodies at `-vv`.
+pub fn level_for_verbosity(verbosity: u8) -> Level {
+    match verbosity {
+        0 => Level::WARN,
+        1 => Level::DEBUG,
+        _ => Level::TRACE,
+    }
+}
+
+/// `rust_log`'s directives, skipping any that don't parse, or the level for
+/// `verbosity` when it has none.
+fn filter(rust_log: Option<&str>, verbosity: u8) -> EnvFilter {
+    EnvFilter::builder()
+        .with_default_directive(LevelFilter::from_level(level_for_verbosity(verbosity)).into())
+        .parse_lossy(rust_log.unwrap_or_default())
+}
+
+/// Installs the global subscriber, filtered by `RUST_LOG` when it is set and
+/// otherwise by `verbosity`. Later calls are ignored.
+pub fn init(verbosity: u8) {
+    let rust_log = std::env::var("RUST_LOG").ok();
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(filter(rust_log.as_deref(), verbosity))
+        .with_writer(std::io::stderr)
+        .without_time()
+        .try_init();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rust_log_overrides_verbosity() {
+        let directives = filter(Some("warn,ra1::providers=trace,bogus=nonsense"), 0).to_string();
+        assert!(directives.contains("ra1::providers=trace"), "{}", directives);
+        assert!(!directives.contains("bogus"), "{}", directives);
+        assert_eq!(filter(None, 1).max_level_hint(), Some(LevelFilter::DEBUG));
+        assert_eq!(filter(Some(""), 2).max_level_hint(), Some(LevelFilter::TRACE));
+    }
+}
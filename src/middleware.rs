@@ -0,0 +1,396 @@
+//! Hooks that run around every call of a wrapped `LLM`, for cross-cutting
+//! behavior such as logging, metrics, or redaction.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::AgentConfig;
+use crate::llm::{LLMRequest, LLMResponse, ModelInfo, LLM};
+use crate::pricing::PricingTable;
+use crate::template::civil_from_days;
+
+/// Observes requests before they are sent and responses after they arrive.
+/// Both hooks default to doing nothing.
+pub trait Middleware: Send + Sync {
+    fn before_request(&self, _request: &LLMRequest) {}
+
+    fn after_response(&self, _response: &LLMResponse) {}
+}
+
+/// Lets callers keep a handle to a middleware, e.g. to read its counters.
+impl<M: Middleware + ?Sized> Middleware for Arc<M> {
+    fn before_request(&self, request: &LLMRequest) {
+        (**self).before_request(request);
+    }
+
+    fn after_response(&self, response: &LLMResponse) {
+        (**self).after_response(response);
+    }
+}
+
+/// Wraps an `LLM` and runs a chain of middlewares around each `invoke`:
+/// `before_request` in the order added, `after_response` in reverse.
+pub struct MiddlewareProvider {
+    inner: Box<dyn LLM>,
+    middlewares: Vec<Box<dyn Middleware>>,
+}
+
+impl MiddlewareProvider {
+    pub fn new(inner: Box<dyn LLM>) -> Self {
+        Self { inner, middlewares: Vec::new() }
+    }
+
+    /// Adds `middleware` to the end of the chain.
+    pub fn with_middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middlewares.push(Box::new(middleware));
+        self
+    }
+}
+
+#[async_trait]
+impl LLM for MiddlewareProvider {
+    async fn invoke(&self, request: &LLMRequest) -> Result<LLMResponse> {
+        for middleware in &self.middlewares {
+            middleware.before_request(request);
+        }
+        let response = self.inner.invoke(request).await?;
+        for middleware in self.middlewares.iter().rev() {
+            middleware.after_response(&response);
+        }
+        Ok(response)
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        self.inner.list_models().await
+    }
+}
+
+// --- Model Override ---
+
+/// Wraps an `LLM` built for one model and sends requests to another, by
+/// naming it in `LLMRequest::model`, without rebuilding the provider.
+pub struct ModelOverride {
+    inner: Box<dyn LLM>,
+    configured_model: String,
+    model: Option<String>,
+}
+
+impl ModelOverride {
+    /// Wraps `inner`, which was built for `configured_model`, with no override.
+    pub fn new(inner: Box<dyn LLM>, configured_model: impl Into<String>) -> Self {
+        Self { inner, configured_model: configured_model.into(), model: None }
+    }
+
+    /// Sends later requests to `model`; naming the configured model clears the override.
+    pub fn set_model(&mut self, model: &str) {
+        self.model = (model != self.configured_model).then(|| model.to_string());
+    }
+}
+
+#[async_trait]
+impl LLM for ModelOverride {
+    /// Requests that already name a model keep it.
+    async fn invoke(&self, request: &LLMRequest) -> Result<LLMResponse> {
+        match &self.model {
+            Some(model) if request.model.is_none() => {
+                let request = LLMRequest { model: Some(model.clone()), ..request.clone() };
+                self.inner.invoke(&request).await
+            }
+            _ => self.inner.invoke(request).await,
+        }
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        self.inner.list_models().await
+    }
+}
+
+// --- Call Counter ---
+
+/// Counts calls and the tokens they used, logging the running totals as an
+/// `INFO` event after each response.
+#[derive(Debug, Default)]
+pub struct CallCounter {
+    calls: AtomicU64,
+    input_tokens: AtomicU64,
+    output_tokens: AtomicU64,
+}
+
+impl CallCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests sent so far, including ones that failed.
+    pub fn calls(&self) -> u64 {
+        self.calls.load(Ordering::Relaxed)
+    }
+
+    /// Input and output tokens of every successful response.
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens.load(Ordering::Relaxed) + self.output_tokens.load(Ordering::Relaxed)
+    }
+}
+
+impl Middleware for CallCounter {
+    fn before_request(&self, _request: &LLMRequest) {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn after_response(&self, response: &LLMResponse) {
+        let input = self.input_tokens.fetch_add(u64::from(response.input_tokens), Ordering::Relaxed)
+            + u64::from(response.input_tokens);
+        let output = self.output_tokens.fetch_add(u64::from(response.output_tokens), Ordering::Relaxed)
+            + u64::from(response.output_tokens);
+        tracing::info!(
+            calls = self.calls(),
+            input_tokens = input,
+            output_tokens = output,
+            total_tokens = input + output,
+            "LLM call completed"
+        );
+    }
+}
+
+// --- Request Logger ---
+
+/// Logs each call's model and sampling parameters before it is sent, and its
+/// usage and latency after, as `DEBUG` events.
+#[derive(Debug, Clone)]
+pub struct RequestLogger {
+    model: String,
+    max_tokens: u32,
+    temperature: f32,
+}
+
+impl RequestLogger {
+    /// Logs requests as sent with `config`'s model and parameters.
+    pub fn new(config: &AgentConfig) -> Self {
+        Self { model: config.model.clone(), max_tokens: config.max_tokens, temperature: config.temperature }
+    }
+}
+
+impl Middleware for RequestLogger {
+    fn before_request(&self, request: &LLMRequest) {
+        tracing::debug!(
+            model = request.model.as_deref().unwrap_or(&self.model),
+            max_tokens = self.max_tokens,
+            temperature = %self.temperature,
+            messages = request.messages.len(),
+            system_prompt_chars = request.system_prompt.chars().count(),
+            "Sending request"
+        );
+    }
+
+    fn after_response(&self, response: &LLMResponse) {
+        tracing::debug!(
+            input_tokens = response.input_tokens,
+            output_tokens = response.output_tokens,
+            stop_reason = response.stop_reason.as_str(),
+            elapsed_ms = response.latency_ms,
+            "Received response"
+        );
+    }
+}
+
+// --- Audit Log ---
+
+/// One line of the audit log. Content is recorded only as SHA-256 hashes, so
+/// the log can prove what was sent without holding it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AuditRecord {
+    /// UTC time the response arrived, as RFC 3339 with milliseconds.
+    pub ts: String,
+    pub session_id: Option<String>,
+    pub model: String,
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    /// `None` when the model has no known pricing.
+    pub cost_usd: Option<f64>,
+    pub latency_ms: u64,
+    /// Hash of the request as JSON: the system prompt and messages.
+    pub request_sha256: String,
+    /// Hash of the response text.
+    pub response_sha256: String,
+}
+
+/// Wraps an `LLM` and appends an [`AuditRecord`] to a JSONL file after every
+/// response. Each call hashes its own request, so concurrent calls through
+/// one logger get the right records. Calls that fail get no record. Write
+/// errors are logged as warnings rather than failing the call.
+pub struct AuditLogger {
+    inner: Box<dyn LLM>,
+    path: PathBuf,
+    file: Mutex<File>,
+    model: String,
+    pricing: PricingTable,
+    session_id: Option<String>,
+}
+
+impl AuditLogger {
+    /// Wraps `inner`, opening `path` for appending, creating it and its
+    /// directory if needed, and records calls with `config`'s pricing, and its
+    /// model unless a request names another.
+    pub fn new(inner: Box<dyn LLM>, path: &Path, config: &AgentConfig) -> Result<Self> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create audit log directory {}", dir.display()))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open audit log {}", path.display()))?;
+        Ok(Self {
+            inner,
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+            model: config.model.clone(),
+            pricing: config.pricing_table(),
+            session_id: None,
+        })
+    }
+
+    /// Tags every record with `session_id`.
+    pub fn with_session_id(mut self, session_id: impl Into<String>) -> Self {
+        self.session_id = Some(session_id.into());
+        self
+    }
+
+    fn record(&self, response: &LLMResponse, model: String, request_sha256: String) -> AuditRecord {
+        AuditRecord {
+            ts: rfc3339_utc(SystemTime::now()),
+            session_id: self.session_id.clone(),
+            cost_usd: self.pricing.lookup(&model).map(|pricing| pricing.cost(response.input_tokens, response.output_tokens)),
+            model,
+            input_tokens: response.input_tokens,
+            output_tokens: response.output_tokens,
+            latency_ms: response.latency_ms,
+            request_sha256,
+            response_sha256: sha256_hex(response.content.as_bytes()),
+        }
+    }
+
+    fn write(&self, record: &AuditRecord) {
+        let mut line = serde_json::to_string(record).expect("audit records always serialize");
+        line.push('\n');
+        // One write per record, so concurrent appenders don't interleave lines.
+        if let Err(e) = self.file.lock().unwrap().write_all(line.as_bytes()) {
+            tracing::warn!(path = %self.path.display(), error = %e, "Failed to write audit record");
+        }
+    }
+}
+
+#[async_trait]
+impl LLM for AuditLogger {
+    async fn invoke(&self, request: &LLMRequest) -> Result<LLMResponse> {
+        let request_sha256 = sha256_hex(&serde_json::to_vec(request)?);
+        let model = request.model.clone().unwrap_or_else(|| self.model.clone());
+        let response = self.inner.invoke(request).await?;
+        self.write(&self.record(&response, model, request_sha256));
+        Ok(response)
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        self.inner.list_models().await
+    }
+}
+
+/// Lowercase hex SHA-256 of `data`.
+fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// `time` as e.g. `2024-06-20T14:03:07.123Z`.
+fn rfc3339_utc(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs() as i64;
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    let time = secs.rem_euclid(86_400);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::Message;
+    use crate::providers::dry_run::DryRunLLM;
+    use crate::AgentConfig;
+
+    #[tokio::test]
+    async fn counter_sees_every_call() {
+        let counter = Arc::new(CallCounter::new());
+        let llm = MiddlewareProvider::new(Box::new(DryRunLLM::new(AgentConfig::default()))).with_middleware(counter.clone());
+        let request = LLMRequest { system_prompt: String::new(), messages: vec![Message::user("hello")], model: None };
+        let first = llm.invoke(&request).await.unwrap();
+        llm.invoke(&request).await.unwrap();
+
+        assert_eq!(counter.calls(), 2);
+        assert_eq!(counter.total_tokens(), 2 * u64::from(first.input_tokens + first.output_tokens));
+    }
+
+    #[tokio::test]
+    async fn audit_log_holds_hashes_not_content() {
+        let path = std::env::temp_dir().join(format!("ra1-audit-{}", std::process::id())).join("audit.jsonl");
+        let _ = std::fs::remove_file(&path);
+        let config = AgentConfig::default();
+        let llm = AuditLogger::new(Box::new(DryRunLLM::new(config.clone())), &path, &config).unwrap().with_session_id("20241015-101010");
+        let request = LLMRequest { system_prompt: String::new(), messages: vec![Message::user("my secret plan")], model: None };
+        let response = llm.invoke(&request).await.unwrap();
+        // Concurrent calls each record their own request and model.
+        let other = LLMRequest { model: Some("claude-3-haiku-20240307".to_string()), ..request.clone() };
+        let (_, other_response) = tokio::join!(llm.invoke(&request), llm.invoke(&other));
+        let other_response = other_response.unwrap();
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert!(!log.contains("secret"), "{}", log);
+        let records: Vec<serde_json::Value> = log.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0]["session_id"], "20241015-101010");
+        assert_eq!(records[0]["input_tokens"], response.input_tokens);
+        assert_eq!(records[0]["request_sha256"], sha256_hex(&serde_json::to_vec(&request).unwrap()));
+        assert_eq!(records[0]["response_sha256"], sha256_hex(response.content.as_bytes()));
+        assert!(records[0]["cost_usd"].is_number());
+        assert_eq!(sha256_hex(b"abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        let haiku = records.iter().find(|record| record["model"] == "claude-3-haiku-20240307").unwrap();
+        assert_eq!(haiku["request_sha256"], sha256_hex(&serde_json::to_vec(&other).unwrap()));
+        assert_eq!(haiku["response_sha256"], sha256_hex(other_response.content.as_bytes()));
+    }
+
+    #[test]
+    fn timestamps_are_rfc3339_utc() {
+        let time = UNIX_EPOCH + std::time::Duration::from_millis(951_827_696_789);
+        assert_eq!(rfc3339_utc(time), "2000-02-29T12:34:56.789Z");
+    }
+
+    #[tokio::test]
+    async fn override_names_the_model_until_switched_back() {
+        let config = AgentConfig::default();
+        let mut llm = ModelOverride::new(Box::new(DryRunLLM::new(config.clone())), config.model.clone());
+        let request = LLMRequest { system_prompt: String::new(), messages: vec![Message::user("hi")], model: None };
+
+        llm.set_model("claude-3-haiku-20240307");
+        assert!(llm.invoke(&request).await.unwrap().content.contains(r#""model": "claude-3-haiku-20240307""#));
+        llm.set_model(&config.model);
+        assert!(llm.model.is_none());
+        assert!(llm.invoke(&request).await.unwrap().content.contains(&format!(r#""model": "{}""#, config.model)));
+    }
+}
@@ -0,0 +1,72 @@
+//! Static facts about Claude models, keyed by model-name prefix like the pricing table.
+
+use crate::suggest;
+
+/// Model IDs accepted by the Anthropic API at the time of writing.
+pub const KNOWN_MODELS: &[&str] = &[
+    "claude-opus-4-1-20250805",
+    "claude-opus-4-20250514",
+    "claude-sonnet-4-20250514",
+    "claude-3-7-sonnet-20250219",
+    "claude-3-7-sonnet-latest",
+    "claude-3-5-sonnet-20241022",
+    "claude-3-5-sonnet-20240620",
+    "claude-3-5-sonnet-latest",
+    "claude-3-5-haiku-20241022",
+    "claude-3-5-haiku-latest",
+    "claude-3-opus-20240229",
+    "claude-3-opus-latest",
+    "claude-3-sonnet-20240229",
+    "claude-3-haiku-20240307",
+];
+
+/// Maximum output tokens per model family; the longest matching prefix wins.
+const MAX_OUTPUT_TOKENS: &[(&str, u32)] = &[
+    ("claude-opus-4", 32_000),
+    ("claude-sonnet-4", 64_000),
+    ("claude-3-7-sonnet", 64_000),
+    ("claude-3-5-sonnet", 8_192),
+    ("claude-3-5-haiku", 8_192),
+    ("claude-3-opus", 4_096),
+    ("claude-3-sonnet", 4_096),
+    ("claude-3-haiku", 4_096),
+];
+
+/// Context window (input plus output tokens) per model family.
+const CONTEXT_WINDOWS: &[(&str, u32)] = &[
+    ("claude-opus-4", 200_000),
+    ("claude-sonnet-4", 200_000),
+    ("claude-3-7-sonnet", 200_000),
+    ("claude-3-5-sonnet", 200_000),
+    ("claude-3-5-haiku", 200_000),
+    ("claude-3-opus", 200_000),
+    ("claude-3-sonnet", 200_000),
+    ("claude-3-haiku", 200_000),
+];
+
+fn lookup<T: Copy>(table: &[(&str, T)], model: &str) -> Option<T> {
+    table
+        .iter()
+        .filter(|(prefix, _)| model.starts_with(prefix))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, value)| *value)
+}
+
+/// The largest `max_tokens` the API accepts for `model`, if known.
+pub fn max_output_tokens(model: &str) -> Option<u32> {
+    lookup(MAX_OUTPUT_TOKENS, model)
+}
+
+/// The most tokens one request to `model` can hold, if known.
+pub fn context_window(model: &str) -> Option<u32> {
+    lookup(CONTEXT_WINDOWS, model)
+}
+
+pub fn is_known_model(model: &str) -> bool {
+    KNOWN_MODELS.contains(&model)
+}
+
+/// The known model closest to `model` by edit distance, for "did you mean" hints.
+pub fn suggest_model(model: &str) -> Option<&'static str> {
+    suggest::closest(model, KNOWN_MODELS.iter().copied())
+}
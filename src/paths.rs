@@ -0,0 +1,180 @@
+//! Filesystem locations used by the agent, following the XDG base directory spec.
+//!
+//! `$XDG_CONFIG_HOME` and `$XDG_DATA_HOME` are honored on every platform when set
+//! to an absolute path; otherwise the platform defaults from the `dirs` crate are
+//! used (`~/.config` and `~/.local/share` on Linux, `~/Library/Application Support`
+//! on macOS, `%APPDATA%` on Windows).
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// Directory name used under the config and data roots.
+pub const APP_DIR: &str = "claude-agent";
+
+/// Picks `$XDG_*` if it is an absolute path, else the platform fallback.
+fn xdg_or(xdg: Option<OsString>, fallback: Option<PathBuf>) -> Option<PathBuf> {
+    xdg.map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or(fallback)
+}
+
+/// Directory holding `config.toml` and the API key.
+pub fn config_dir() -> Option<PathBuf> {
+    xdg_or(std::env::var_os("XDG_CONFIG_HOME"), dirs::config_dir()).map(|dir| dir.join(APP_DIR))
+}
+
+/// Directory for cached data and saved sessions.
+pub fn data_dir() -> Option<PathBuf> {
+    xdg_or(std::env::var_os("XDG_DATA_HOME"), dirs::data_dir()).map(|dir| dir.join(APP_DIR))
+}
+
+pub fn config_file() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config.toml"))
+}
+
+/// Per-project overrides, relative to the current directory.
+pub const PROJECT_CONFIG_FILE: &str = ".ra1/config.toml";
+
+/// `.ra1/config.toml` under the current directory.
+pub fn project_config_file() -> Option<PathBuf> {
+    std::env::current_dir().ok().map(|dir| dir.join(PROJECT_CONFIG_FILE))
+}
+
+pub fn key_file() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("api_key"))
+}
+
+/// Key used for OpenAI embeddings.
+pub fn openai_key_file() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("openai_api_key"))
+}
+
+/// Key used for Together AI.
+pub fn together_key_file() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("together_api_key"))
+}
+
+/// Named message templates for `ask --template`, one `<name>.txt` each.
+pub fn templates_dir() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("templates"))
+}
+
+pub fn rag_store_file() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("rag_store.json"))
+}
+
+pub fn sessions_dir() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("sessions"))
+}
+
+/// JSONL log of every API call's token usage, read by `cost`.
+pub fn usage_log_file() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("usage.jsonl"))
+}
+
+/// The pre-XDG key location, still read if the new one doesn't exist.
+pub fn legacy_key_file() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".api").join("anthropic1"))
+}
+
+/// Returns the key file to read for `configured`. If `configured` is the default
+/// location, doesn't exist, and a legacy key does, the legacy file is used and a
+/// notice is printed to stderr the first time this happens.
+pub fn resolve_key_file(configured: &Path) -> PathBuf {
+    if configured.exists() || key_file().as_deref() != Some(configured) {
+        return configured.to_path_buf();
+    }
+    match legacy_key_file() {
+        Some(legacy) if legacy.exists() => {
+            notify_legacy_key_once(&legacy, configured);
+            legacy
+        }
+        _ => configured.to_path_buf(),
+    }
+}
+
+fn notify_legacy_key_once(legacy: &Path, configured: &Path) {
+    let marker = data_dir().map(|dir| dir.join(".legacy-key-notice"));
+    if marker.as_deref().is_some_and(Path::exists) {
+        return;
+    }
+    eprintln!(
+        "Note: reading API key from legacy location {}. Move it to {} to silence this notice.",
+        legacy.display(),
+        configured.display()
+    );
+    if let Some(marker) = marker {
+        if let Some(dir) = marker.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        let _ = std::fs::write(&marker, "");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn absolute_xdg_value_wins() {
+        let resolved = xdg_or(Some("/custom/config".into()), Some(PathBuf::from("/fallback")));
+        assert_eq!(resolved, Some(PathBuf::from("/custom/config")));
+    }
+
+    #[test]
+    fn relative_xdg_value_is_ignored() {
+        let resolved = xdg_or(Some("relative/config".into()), Some(PathBuf::from("/fallback")));
+        assert_eq!(resolved, Some(PathBuf::from("/fallback")));
+    }
+
+    #[test]
+    fn missing_xdg_value_uses_fallback() {
+        assert_eq!(xdg_or(None, Some(PathBuf::from("/fallback"))), Some(PathBuf::from("/fallback")));
+        assert_eq!(xdg_or(None, None), None);
+    }
+
+    #[test]
+    fn files_live_under_app_dir() {
+        let config = config_dir().unwrap();
+        assert!(config.ends_with(APP_DIR));
+        assert_eq!(config_file().unwrap(), config.join("config.toml"));
+        assert_eq!(key_file().unwrap(), config.join("api_key"));
+        assert_eq!(sessions_dir().unwrap(), data_dir().unwrap().join("sessions"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn linux_fallbacks_match_xdg_defaults() {
+        let home = dirs::home_dir().unwrap();
+        if std::env::var_os("XDG_CONFIG_HOME").is_none() {
+            assert_eq!(config_dir().unwrap(), home.join(".config").join(APP_DIR));
+        }
+        if std::env::var_os("XDG_DATA_HOME").is_none() {
+            assert_eq!(data_dir().unwrap(), home.join(".local/share").join(APP_DIR));
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn macos_fallbacks_use_application_support() {
+        let support = dirs::home_dir().unwrap().join("Library/Application Support");
+        if std::env::var_os("XDG_CONFIG_HOME").is_none() {
+            assert_eq!(config_dir().unwrap(), support.join(APP_DIR));
+        }
+        if std::env::var_os("XDG_DATA_HOME").is_none() {
+            assert_eq!(data_dir().unwrap(), support.join(APP_DIR));
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn windows_fallbacks_use_roaming_appdata() {
+        let roaming = dirs::home_dir().unwrap().join("AppData").join("Roaming");
+        if std::env::var_os("XDG_CONFIG_HOME").is_none() {
+            assert_eq!(config_dir().unwrap(), roaming.join(APP_DIR));
+        }
+        if std::env::var_os("XDG_DATA_HOME").is_none() {
+            assert_eq!(data_dir().unwrap(), roaming.join(APP_DIR));
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Prices for a model in USD per million tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPricing {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+impl ModelPricing {
+    pub const fn new(input_per_million: f64, output_per_million: f64) -> Self {
+        Self {
+            input_per_million,
+            output_per_million,
+        }
+    }
+
+    /// Cost in USD of a call with the given token usage.
+    pub fn cost(&self, input_tokens: u32, output_tokens: u32) -> f64 {
+        (input_tokens as f64 / 1_000_000.0) * self.input_per_million
+            + (output_tokens as f64 / 1_000_000.0) * self.output_per_million
+    }
+}
+
+/// Built-in prices keyed by model-name prefix. Dated variants and `-latest`
+/// aliases resolve through the longest matching prefix.
+const BUILTIN_PRICING: &[(&str, ModelPricing)] = &[
+    ("claude-opus-4", ModelPricing::new(15.00, 75.00)),
+    ("claude-sonnet-4", ModelPricing::new(3.00, 15.00)),
+    ("claude-3-7-sonnet", ModelPricing::new(3.00, 15.00)),
+    ("claude-3-5-sonnet", ModelPricing::new(3.00, 15.00)),
+    ("claude-3-5-haiku", ModelPricing::new(0.80, 4.00)),
+    ("claude-3-opus", ModelPricing::new(15.00, 75.00)),
+    ("claude-3-sonnet", ModelPricing::new(3.00, 15.00)),
+    ("claude-3-haiku", ModelPricing::new(0.25, 1.25)),
+];
+
+/// Maps model names to prices, combining the built-in table with config overrides.
+#[derive(Debug, Clone)]
+pub struct PricingTable {
+    entries: HashMap<String, ModelPricing>,
+}
+
+impl PricingTable {
+    /// The table shipped with the agent.
+    pub fn builtin() -> Self {
+        Self {
+            entries: BUILTIN_PRICING
+                .iter()
+                .map(|(prefix, pricing)| (prefix.to_string(), *pricing))
+                .collect(),
+        }
+    }
+
+    /// The built-in table with `overrides` replacing or extending its entries.
+    pub fn with_overrides(overrides: &HashMap<String, ModelPricing>) -> Self {
+        let mut table = Self::builtin();
+        table
+            .entries
+            .extend(overrides.iter().map(|(k, v)| (k.clone(), *v)));
+        table
+    }
+
+    /// Finds the pricing for `model` by longest matching prefix.
+    pub fn lookup(&self, model: &str) -> Option<ModelPricing> {
+        self.entries
+            .iter()
+            .filter(|(prefix, _)| model.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, pricing)| *pricing)
+    }
+}
+
+impl Default for PricingTable {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
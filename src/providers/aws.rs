@@ -0,0 +1,107 @@
+//! AWS credentials and request signing, from the AWS SDK's `aws-config` and
+//! `aws-sigv4` crates. Requests themselves go through the shared reqwest
+//! client so proxy, CA, and timeout settings still apply.
+
+use anyhow::{Context, Result};
+use aws_config::{BehaviorVersion, Region};
+use aws_credential_types::provider::{ProvideCredentials, SharedCredentialsProvider};
+use aws_sigv4::http_request::{self, SignableBody, SignableRequest, SigningSettings};
+use aws_sigv4::sign::v4;
+use aws_smithy_runtime_api::client::identity::Identity;
+use reqwest::Url;
+use std::fmt::Write;
+use std::time::{Duration, SystemTime};
+
+/// An access key pair, with the session token and expiry of temporary
+/// credentials. Its `Debug` output hides the secret and the session token.
+pub use aws_credential_types::Credentials as AwsCredentials;
+
+// --- Credentials ---
+
+/// The standard AWS credential chain, as the AWS SDKs resolve it: environment
+/// variables, the shared config and credentials files (including SSO and
+/// `credential_process` profiles), web identity, then ECS task or EC2 instance roles.
+pub async fn credential_chain(region: &str) -> Result<SharedCredentialsProvider> {
+    aws_config::defaults(BehaviorVersion::latest())
+        .region(Region::new(region.to_string()))
+        .load()
+        .await
+        .credentials_provider()
+        .context("The AWS credential chain is not available")
+}
+
+/// Resolves credentials from `provider`, e.g. [`credential_chain`].
+pub async fn load_credentials(provider: &SharedCredentialsProvider) -> Result<AwsCredentials> {
+    provider.provide_credentials().await.context(
+        "No AWS credentials found: set AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY, add a profile to \
+         ~/.aws/credentials, run `aws sso login` for an SSO profile, or run with an instance profile",
+    )
+}
+
+/// Whether `credentials` expire within `margin`; long-lived keys never do.
+pub fn expires_within(credentials: &AwsCredentials, margin: Duration) -> bool {
+    credentials.expiry().is_some_and(|expiry| SystemTime::now() + margin >= expiry)
+}
+
+// --- Signature Version 4 ---
+
+/// Percent-encodes everything except the unreserved characters, as SigV4 requires.
+pub fn uri_encode(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            out.push(byte as char);
+        } else {
+            write!(out, "%{:02X}", byte).unwrap();
+        }
+    }
+    out
+}
+
+/// Headers that authenticate a request: `x-amz-date`, `x-amz-security-token`
+/// for temporary credentials, and `authorization`. `headers` are the other
+/// headers to sign, besides `host`; the request must send them unchanged.
+pub fn sign(
+    credentials: &AwsCredentials,
+    region: &str,
+    service: &str,
+    method: &str,
+    url: &Url,
+    headers: &[(&str, &str)],
+    body: &[u8],
+) -> Result<Vec<(String, String)>> {
+    let identity: Identity = credentials.clone().into();
+    let params = v4::SigningParams::builder()
+        .identity(&identity)
+        .region(region)
+        .name(service)
+        .time(SystemTime::now())
+        .settings(SigningSettings::default())
+        .build()
+        .context("Invalid SigV4 signing parameters")?
+        .into();
+    let signable = SignableRequest::new(method, url.as_str(), headers.iter().copied(), SignableBody::Bytes(body))
+        .context("Request can't be signed")?;
+    let (instructions, _signature) = http_request::sign(signable, &params).context("Failed to sign the request")?.into_parts();
+    Ok(instructions.headers().map(|(name, value)| (name.to_string(), value.to_string())).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_all_but_unreserved_characters() {
+        assert_eq!(uri_encode("anthropic.claude-3-5-sonnet-20240620-v1:0"), "anthropic.claude-3-5-sonnet-20240620-v1%3A0");
+        assert_eq!(uri_encode("a b/c~"), "a%20b%2Fc~");
+    }
+
+    #[test]
+    fn temporary_credentials_expire() {
+        let long_lived = AwsCredentials::new("A", "B", None, None, "test");
+        assert!(!expires_within(&long_lived, Duration::from_secs(300)));
+        let expiring = AwsCredentials::new("A", "B", Some("C".to_string()), Some(SystemTime::now() + Duration::from_secs(60)), "test");
+        assert!(expires_within(&expiring, Duration::from_secs(300)));
+        assert!(!format!("{:?}", expiring).contains('B'));
+    }
+}
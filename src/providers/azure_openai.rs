@@ -0,0 +1,284 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+
+use crate::config::{AgentConfig, OPENAI_MAX_TEMPERATURE};
+use crate::llm::{LLMRequest, LLMResponse, LLM};
+use crate::providers::{http, openai_compat};
+
+/// The Azure OpenAI REST API version used when none is configured.
+pub const DEFAULT_AZURE_API_VERSION: &str = "2024-06-01";
+
+/// The token audience for Azure Cognitive Services, requested from managed identity.
+const COGNITIVE_SERVICES_RESOURCE: &str = "https://cognitiveservices.azure.com";
+
+/// Refresh managed identity tokens this long before they expire.
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(300);
+
+// --- Configuration ---
+
+/// `AgentConfig` plus the fields that route a request to an Azure OpenAI deployment.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AzureAgentConfig {
+    /// Shared generation settings (max_tokens, temperature, timeouts, proxy, ...).
+    /// `model`, and `LLMRequest::model`, are ignored; the deployment decides which model runs.
+    #[serde(default)]
+    pub base: AgentConfig,
+    /// The `{resource}` in `{resource}.openai.azure.com`.
+    pub resource_name: String,
+    pub deployment_name: String,
+    #[serde(default = "default_azure_api_version")]
+    pub api_version: String,
+    /// A file holding an `api-key`. When unset, managed identity is used instead.
+    #[serde(default)]
+    pub api_key_path: Option<PathBuf>,
+}
+
+fn default_azure_api_version() -> String {
+    DEFAULT_AZURE_API_VERSION.to_string()
+}
+
+impl AzureAgentConfig {
+    pub fn new(base: AgentConfig, resource_name: impl Into<String>, deployment_name: impl Into<String>) -> Self {
+        Self {
+            base,
+            resource_name: resource_name.into(),
+            deployment_name: deployment_name.into(),
+            api_version: default_azure_api_version(),
+            api_key_path: None,
+        }
+    }
+
+    /// The resource's API root.
+    pub fn api_base(&self) -> String {
+        format!("https://{}.openai.azure.com", self.resource_name)
+    }
+
+    /// The chat completions path for the configured deployment, relative to `api_base`.
+    pub fn chat_completions_path(&self) -> String {
+        format!("/openai/deployments/{}/chat/completions?api-version={}", self.deployment_name, self.api_version)
+    }
+
+    /// The chat completions endpoint for the configured deployment.
+    pub fn endpoint(&self) -> String {
+        format!("{}{}", self.api_base(), self.chat_completions_path())
+    }
+}
+
+// --- Authentication ---
+
+enum AzureAuth {
+    /// A static key sent in the `api-key` header.
+    ApiKey(String),
+    /// Tokens fetched from the App Service / Functions managed identity endpoint.
+    ManagedIdentity {
+        endpoint: String,
+        identity_header: String,
+        cached: Mutex<Option<CachedToken>>,
+    },
+}
+
+struct CachedToken {
+    token: String,
+    expires_at: SystemTime,
+}
+
+#[derive(Deserialize, Debug)]
+struct MsiTokenResponse {
+    access_token: String,
+    /// Seconds since the Unix epoch; the endpoint sends it as a string.
+    expires_on: String,
+}
+
+// --- Azure OpenAI Provider ---
+
+/// A provider for chat completions on an Azure OpenAI deployment.
+pub struct AzureOpenAIProvider {
+    client: Client,
+    base_url: String,
+    config: AzureAgentConfig,
+    auth: AzureAuth,
+}
+
+impl AzureOpenAIProvider {
+    /// Authenticates with the key at `api_key_path` when set, otherwise with
+    /// managed identity via the `MSI_ENDPOINT` and `IDENTITY_HEADER` variables.
+    pub async fn new(config: AzureAgentConfig) -> Result<Self> {
+        config.base.validate_sampling(OPENAI_MAX_TEMPERATURE)?;
+        config.base.warn_unsent_sampling("Azure OpenAI", openai_compat::UNSENT_SAMPLING);
+        let auth = match &config.api_key_path {
+            Some(path) => {
+                let key = tokio::fs::read_to_string(path)
+                    .await
+                    .with_context(|| format!("Failed to read Azure API key from {}", path.display()))?;
+                AzureAuth::ApiKey(key.trim().to_string())
+            }
+            None => match (std::env::var("MSI_ENDPOINT"), std::env::var("IDENTITY_HEADER")) {
+                (Ok(endpoint), Ok(identity_header)) => AzureAuth::ManagedIdentity {
+                    endpoint,
+                    identity_header,
+                    cached: Mutex::new(None),
+                },
+                _ => anyhow::bail!(
+                    "No Azure credentials: set api_key_path, or run with managed identity (MSI_ENDPOINT and IDENTITY_HEADER)"
+                ),
+            },
+        };
+        Self::with_auth(config, auth)
+    }
+
+    fn with_auth(config: AzureAgentConfig, auth: AzureAuth) -> Result<Self> {
+        Ok(Self { client: http::build_client(&config.base)?, base_url: config.api_base(), config, auth })
+    }
+
+    /// Points the provider at a different server, e.g. a private endpoint or a mock.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Returns a managed identity access token, fetching a new one when the
+    /// cached token is missing or close to expiry.
+    async fn managed_identity_token(
+        &self,
+        endpoint: &str,
+        identity_header: &str,
+        cached: &Mutex<Option<CachedToken>>,
+    ) -> Result<String> {
+        let mut cached = cached.lock().await;
+        if let Some(token) = cached.as_ref() {
+            if SystemTime::now() + TOKEN_REFRESH_MARGIN < token.expires_at {
+                return Ok(token.token.clone());
+            }
+        }
+
+        let response = self
+            .client
+            .get(endpoint)
+            .query(&[("resource", COGNITIVE_SERVICES_RESOURCE), ("api-version", "2019-08-01")])
+            .header("X-IDENTITY-HEADER", identity_header)
+            .send()
+            .await
+            .map_err(|e| http::transport_error(&self.config.base, e, "Failed to request managed identity token"))?;
+        let parsed: MsiTokenResponse = http::check_status(response)
+            .await?
+            .json()
+            .await
+            .context("Failed to parse managed identity token response")?;
+        let expires_on: u64 = parsed
+            .expires_on
+            .parse()
+            .context("Managed identity token has an invalid expires_on")?;
+
+        let token = parsed.access_token;
+        *cached = Some(CachedToken {
+            token: token.clone(),
+            expires_at: UNIX_EPOCH + Duration::from_secs(expires_on),
+        });
+        Ok(token)
+    }
+
+    async fn authorize(&self, builder: reqwest::RequestBuilder) -> Result<reqwest::RequestBuilder> {
+        Ok(match &self.auth {
+            AzureAuth::ApiKey(key) => builder.header("api-key", key),
+            AzureAuth::ManagedIdentity { endpoint, identity_header, cached } => {
+                builder.bearer_auth(self.managed_identity_token(endpoint, identity_header, cached).await?)
+            }
+        })
+    }
+}
+
+#[async_trait]
+impl LLM for AzureOpenAIProvider {
+    async fn invoke(&self, request: &LLMRequest) -> Result<LLMResponse> {
+        let body = openai_compat::build_request(&self.config.base, None, request);
+
+        let url = format!("{}{}", self.base_url, self.config.chat_completions_path());
+        // Keys and tokens travel in headers, never in the body.
+        http::trace_body(&url, &body, "");
+        let mut builder = self.authorize(self.client.post(url)).await?;
+        for (name, value) in &self.config.base.extra_headers {
+            builder = builder.header(name, value);
+        }
+
+        let started = Instant::now();
+        let response = builder
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| http::transport_error(&self.config.base, e, "Failed to send request to Azure OpenAI"))?;
+
+        let parsed: openai_compat::ChatResponse = http::check_status(response)
+            .await?
+            .json()
+            .await
+            .map_err(|e| http::transport_error(&self.config.base, e, "Failed to parse Azure OpenAI response"))?;
+
+        Ok(parsed.into_llm_response(started.elapsed().as_millis() as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::Message;
+    use crate::providers::test_support::{key_file, mock_server};
+
+    const REPLY: &str = r#"{"choices":[{"message":{"content":"pong"},"finish_reason":"stop"}],"usage":{"prompt_tokens":4,"completion_tokens":1}}"#;
+
+    fn request() -> LLMRequest {
+        LLMRequest { system_prompt: "Be brief.".to_string(), messages: vec![Message::user("ping")], model: Some("ignored".to_string()) }
+    }
+
+    #[tokio::test]
+    async fn sends_the_key_to_the_deployment() {
+        let key_file = key_file("azure-key\n");
+        let (url, server) = mock_server(vec![("200 OK", REPLY)]).await;
+
+        let base = AgentConfig {
+            extra_headers: std::collections::HashMap::from([("x-team".to_string(), "search".to_string())]),
+            ..AgentConfig::default()
+        };
+        let config = AzureAgentConfig { api_key_path: Some(key_file.path().to_path_buf()), ..AzureAgentConfig::new(base, "res", "gpt4o-prod") };
+        let provider = AzureOpenAIProvider::new(config).await.unwrap().with_base_url(url);
+        let response = provider.invoke(&request()).await.unwrap();
+
+        let raw = server.await.unwrap().remove(0);
+        assert!(raw.starts_with("POST /openai/deployments/gpt4o-prod/chat/completions?api-version=2024-06-01 "), "{}", raw);
+        let headers = raw.to_ascii_lowercase();
+        assert!(headers.contains("api-key: azure-key\r\n") && headers.contains("x-team: search\r\n"), "{}", raw);
+        assert!(!raw.contains("\"model\""), "the deployment picks the model: {}", raw);
+        assert!(raw.contains(r#""messages":[{"role":"system","content":"Be brief."},{"role":"user","content":"ping"}]"#), "{}", raw);
+        assert_eq!(response.content, "pong");
+        assert_eq!((response.input_tokens, response.output_tokens), (4, 1));
+        assert_eq!(response.stop_reason, "end_turn");
+    }
+
+    #[tokio::test]
+    async fn managed_identity_tokens_are_fetched_once_and_reused() {
+        let expires_on = (SystemTime::now() + Duration::from_secs(3600)).duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let token = format!(r#"{{"access_token":"msi-token","expires_on":"{}"}}"#, expires_on);
+        let (url, server) = mock_server(vec![("200 OK", token), ("200 OK", REPLY.to_string()), ("200 OK", REPLY.to_string())]).await;
+
+        let auth = AzureAuth::ManagedIdentity {
+            endpoint: format!("{}/msi/token", url),
+            identity_header: "identity".to_string(),
+            cached: Mutex::new(None),
+        };
+        let config = AzureAgentConfig::new(AgentConfig::default(), "res", "gpt4o-prod");
+        let provider = AzureOpenAIProvider::with_auth(config, auth).unwrap().with_base_url(url);
+        provider.invoke(&request()).await.unwrap();
+        provider.invoke(&request()).await.unwrap();
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("GET /msi/token?resource=https%3A%2F%2Fcognitiveservices.azure.com&api-version=2019-08-01 "), "{}", requests[0]);
+        assert!(requests[0].to_ascii_lowercase().contains("x-identity-header: identity\r\n"), "{}", requests[0]);
+        for chat in &requests[1..] {
+            assert!(chat.to_ascii_lowercase().contains("authorization: bearer msi-token\r\n"), "{}", chat);
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use aws_credential_types::provider::SharedCredentialsProvider;
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use crate::config::{AgentConfig, CLAUDE_MAX_TEMPERATURE};
+use crate::llm::{self, LLMRequest, LLMResponse, LLM};
+use crate::providers::{aws, claude, http};
+
+pub use crate::providers::aws::AwsCredentials;
+
+/// The `anthropic_version` Bedrock expects in place of the `anthropic-version` header.
+pub const BEDROCK_ANTHROPIC_VERSION: &str = "bedrock-2023-05-31";
+
+/// The service name requests are signed for.
+const SIGNING_SERVICE: &str = "bedrock";
+
+/// Reload temporary credentials this long before they expire.
+const CREDENTIAL_REFRESH_MARGIN: Duration = Duration::from_secs(300);
+
+// --- Configuration ---
+
+/// `AgentConfig` plus the fields that route a request to a Claude model on Bedrock.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BedrockAgentConfig {
+    /// Shared generation settings (max_tokens, temperature, timeouts, proxy, ...).
+    /// `model` is ignored; `model_id` picks the model.
+    #[serde(default)]
+    pub base: AgentConfig,
+    /// AWS region, e.g. `us-east-1`.
+    pub region: String,
+    /// Bedrock model ID, e.g. `anthropic.claude-3-5-sonnet-20240620-v1:0`.
+    pub model_id: String,
+}
+
+impl BedrockAgentConfig {
+    pub fn new(base: AgentConfig, region: impl Into<String>, model_id: impl Into<String>) -> Self {
+        Self { base, region: region.into(), model_id: model_id.into() }
+    }
+
+    /// The Bedrock runtime endpoint for the configured region.
+    pub fn endpoint(&self) -> String {
+        format!("https://bedrock-runtime.{}.amazonaws.com", self.region)
+    }
+}
+
+/// The Anthropic Messages body Bedrock's `InvokeModel` takes: no `model`
+/// (the URL names it), and the API version in the body.
+fn build_body(config: &AgentConfig, request: &LLMRequest) -> Result<Vec<u8>> {
+    let mut body = serde_json::to_value(claude::build_request(config, request))?;
+    let fields = body.as_object_mut().context("Claude request did not serialize to an object")?;
+    fields.remove("model");
+    fields.insert("anthropic_version".to_string(), BEDROCK_ANTHROPIC_VERSION.into());
+    Ok(serde_json::to_vec(&body)?)
+}
+
+// --- AWS Bedrock Provider ---
+
+/// A provider for Claude models served through the AWS Bedrock runtime.
+pub struct AwsBedrockProvider {
+    client: Client,
+    config: BedrockAgentConfig,
+    endpoint: String,
+    credentials: Mutex<AwsCredentials>,
+    /// Where `credentials` came from, to reload them before they expire;
+    /// `None` for credentials given directly.
+    chain: Option<SharedCredentialsProvider>,
+}
+
+impl AwsBedrockProvider {
+    /// Authenticates with the standard AWS credential chain: environment
+    /// variables, `~/.aws` profiles (including SSO), then ECS task or EC2 instance roles.
+    pub async fn new(config: BedrockAgentConfig) -> Result<Self> {
+        config.base.validate_sampling(CLAUDE_MAX_TEMPERATURE)?;
+        let client = http::build_client(&config.base)?;
+        let chain = aws::credential_chain(&config.region).await?;
+        let credentials = aws::load_credentials(&chain).await?;
+        Ok(Self { chain: Some(chain), ..Self::build(client, config, credentials) })
+    }
+
+    /// Authenticates with the given credentials instead of the credential chain.
+    pub fn with_credentials(config: BedrockAgentConfig, credentials: AwsCredentials) -> Result<Self> {
+        config.base.validate_sampling(CLAUDE_MAX_TEMPERATURE)?;
+        Ok(Self::build(http::build_client(&config.base)?, config, credentials))
+    }
+
+    fn build(client: Client, config: BedrockAgentConfig, credentials: AwsCredentials) -> Self {
+        config.base.warn_unsent_sampling("Bedrock", claude::UNSENT_SAMPLING);
+        Self {
+            client,
+            endpoint: config.endpoint(),
+            config,
+            credentials: Mutex::new(credentials),
+            chain: None,
+        }
+    }
+
+    /// Points the provider at a different server, e.g. a VPC endpoint or a mock.
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+        self
+    }
+
+    /// The current credentials, reloading temporary ones that are about to expire.
+    async fn credentials(&self) -> Result<AwsCredentials> {
+        let mut credentials = self.credentials.lock().await;
+        if let Some(chain) = self.chain.as_ref().filter(|_| aws::expires_within(&credentials, CREDENTIAL_REFRESH_MARGIN)) {
+            *credentials = aws::load_credentials(chain).await?;
+        }
+        Ok(credentials.clone())
+    }
+}
+
+#[async_trait]
+impl LLM for AwsBedrockProvider {
+    async fn invoke(&self, request: &LLMRequest) -> Result<LLMResponse> {
+        llm::validate_messages(&request.messages)?;
+        let body = build_body(&self.config.base, request)?;
+        let url = Url::parse(&format!("{}/model/{}/invoke", self.endpoint, aws::uri_encode(request.model.as_deref().unwrap_or(&self.config.model_id))))
+            .with_context(|| format!("Invalid Bedrock endpoint {}", self.endpoint))?;
+        let headers = [("content-type", "application/json"), ("accept", "application/json")];
+        let signed = aws::sign(
+            &self.credentials().await?,
+            &self.config.region,
+            SIGNING_SERVICE,
+            "POST",
+            &url,
+            &headers,
+            &body,
+        )?;
+
+        let mut builder = self.client.post(url);
+        for (name, value) in headers {
+            builder = builder.header(name, value);
+        }
+        for (name, value) in signed {
+            builder = builder.header(name, value);
+        }
+        for (name, value) in &self.config.base.extra_headers {
+            builder = builder.header(name, value);
+        }
+
+        let started = Instant::now();
+        let response = builder
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| http::transport_error(&self.config.base, e, "Failed to send request to AWS Bedrock"))?;
+
+        // Bedrock returns the Anthropic Messages response unchanged.
+        let parsed: claude::NonStreamingResponse = http::check_status(response)
+            .await?
+            .json()
+            .await
+            .map_err(|e| http::transport_error(&self.config.base, e, "Failed to parse AWS Bedrock response"))?;
+
+        Ok(parsed.into_llm_response(started.elapsed().as_millis() as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::Message;
+    use crate::providers::test_support::mock_reply;
+    use aws_credential_types::provider::{future, ProvideCredentials};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::SystemTime;
+
+    /// A credential chain that hands out fresh hour-long credentials, counting the loads.
+    #[derive(Debug, Default)]
+    struct CountingChain(Arc<AtomicUsize>);
+
+    impl ProvideCredentials for CountingChain {
+        fn provide_credentials<'a>(&'a self) -> future::ProvideCredentials<'a>
+        where
+            Self: 'a,
+        {
+            let load = self.0.fetch_add(1, Ordering::SeqCst) + 1;
+            future::ProvideCredentials::ready(Ok(AwsCredentials::new(
+                format!("AKIDFRESH{}", load),
+                "fresh-secret",
+                Some("fresh-session".to_string()),
+                Some(SystemTime::now() + Duration::from_secs(3600)),
+                "chain",
+            )))
+        }
+    }
+
+    #[tokio::test]
+    async fn signs_and_wraps_the_messages_body() {
+        let (url, server) = mock_reply(r#"{"content":[{"type":"text","text":"pong"}],"stop_reason":"end_turn","stop_sequence":null,"usage":{"input_tokens":9,"output_tokens":1}}"#).await;
+        let config = BedrockAgentConfig::new(AgentConfig::default(), "us-east-1", "anthropic.claude-3-5-sonnet-20240620-v1:0");
+        let credentials = AwsCredentials::new("AKIDEXAMPLE", "secret", Some("session".to_string()), None, "test");
+        let provider = AwsBedrockProvider::with_credentials(config, credentials).unwrap().with_endpoint(url);
+        let response = provider
+            .invoke(&LLMRequest { system_prompt: "Be brief.".to_string(), messages: vec![Message::user("ping")], model: None })
+            .await
+            .unwrap();
+
+        let raw = server.await.unwrap();
+        let lower = raw.to_ascii_lowercase();
+        assert!(raw.starts_with("POST /model/anthropic.claude-3-5-sonnet-20240620-v1%3A0/invoke "), "{}", raw);
+        assert!(lower.contains("authorization: aws4-hmac-sha256 credential=akidexample/"), "{}", raw);
+        assert!(lower.contains("/us-east-1/bedrock/aws4_request"), "{}", raw);
+        assert!(lower.contains("x-amz-security-token: session\r\n"), "{}", raw);
+        assert!(raw.contains(r#""anthropic_version":"bedrock-2023-05-31""#), "{}", raw);
+        assert!(!raw.contains(r#""model""#), "{}", raw);
+        assert_eq!(response.content, "pong");
+        assert_eq!((response.input_tokens, response.output_tokens), (9, 1));
+    }
+
+    #[tokio::test]
+    async fn expiring_chain_credentials_are_reloaded() {
+        let loads = Arc::new(AtomicUsize::new(0));
+        let config = BedrockAgentConfig::new(AgentConfig::default(), "us-east-1", "anthropic.claude-3-5-sonnet-20240620-v1:0");
+        // Inside the refresh margin, but not yet expired.
+        let expiry = SystemTime::now() + CREDENTIAL_REFRESH_MARGIN / 2;
+        let stale = AwsCredentials::new("AKIDSTALE", "stale-secret", Some("stale-session".to_string()), Some(expiry), "chain");
+        let provider = AwsBedrockProvider {
+            chain: Some(SharedCredentialsProvider::new(CountingChain(loads.clone()))),
+            ..AwsBedrockProvider::with_credentials(config, stale).unwrap()
+        };
+
+        assert_eq!(provider.credentials().await.unwrap().access_key_id(), "AKIDFRESH1");
+        // The reloaded credentials are kept until they near expiry in turn.
+        assert_eq!(provider.credentials().await.unwrap().access_key_id(), "AKIDFRESH1");
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn given_credentials_are_never_reloaded() {
+        let config = BedrockAgentConfig::new(AgentConfig::default(), "us-east-1", "anthropic.claude-3-5-sonnet-20240620-v1:0");
+        let expired = AwsCredentials::new("AKIDGIVEN", "secret", None, Some(SystemTime::now() - Duration::from_secs(60)), "test");
+        let provider = AwsBedrockProvider::with_credentials(config, expired).unwrap();
+        assert_eq!(provider.credentials().await.unwrap().access_key_id(), "AKIDGIVEN");
+    }
+}
@@ -0,0 +1,770 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::config::{AgentConfig, CLAUDE_MAX_TEMPERATURE};
+use crate::error::ApiError;
+use crate::keystore;
+use crate::llm::{self, Content, ImageSource, LLMRequest, LLMResponse, Message, ModelInfo, ResponseFormat, LLM};
+use crate::providers::http;
+use crate::tokens::{CharHeuristicEstimator, TokenEstimator};
+
+// --- API Data Structures (Unchanged) ---
+#[derive(Deserialize, Debug)]
+struct Usage {
+    input_tokens: u32,
+    output_tokens: u32,
+    cache_read_input_tokens: Option<u32>,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct ClaudeRequest<'a> {
+    model: String,
+    max_tokens: u32,
+    /// Omitted when `top_p` is set: the API asks for one or the other, and
+    /// newer models reject both.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<u32>,
+    /// Not a documented Messages API parameter, which rejects unknown fields;
+    /// sent only when set and `api_base_url` isn't Anthropic's, for gateways
+    /// and endpoints that accept it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    system: &'a str,
+    messages: Vec<ClaudeMessage<'a>>,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    stop_sequences: &'a [String],
+    /// Structured output; omitted for plain text.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output_format: Option<serde_json::Value>,
+    // Omitted when false; batch request params don't accept it.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+}
+
+/// Request envelope for the Messages Batches API.
+#[derive(Serialize, Debug)]
+struct BatchCreateRequest<'a> {
+    requests: Vec<BatchRequestItem<'a>>,
+}
+
+#[derive(Serialize, Debug)]
+struct BatchRequestItem<'a> {
+    custom_id: &'a str,
+    params: ClaudeRequest<'a>,
+}
+
+/// The state of a submitted message batch, as returned by the Batches API.
+#[derive(Deserialize, Debug, Clone)]
+pub struct BatchStatus {
+    pub id: String,
+    /// `in_progress`, `canceling`, or `ended`.
+    pub processing_status: String,
+    pub request_counts: BatchRequestCounts,
+    pub results_url: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct BatchRequestCounts {
+    pub processing: u32,
+    pub succeeded: u32,
+    pub errored: u32,
+    pub canceled: u32,
+    pub expired: u32,
+}
+
+impl BatchStatus {
+    pub fn is_ended(&self) -> bool {
+        self.processing_status == "ended"
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct BatchResultLine {
+    custom_id: String,
+    result: BatchResultBody,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum BatchResultBody {
+    Succeeded { message: NonStreamingResponse },
+    Errored { error: serde_json::Value },
+    Canceled,
+    Expired,
+}
+
+/// One page of `GET /v1/models`.
+#[derive(Deserialize, Debug)]
+struct ModelsPage {
+    data: Vec<ModelInfo>,
+    #[serde(default)]
+    has_more: bool,
+    last_id: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+struct ClaudeMessage<'a> {
+    role: &'a str,
+    content: Vec<ClaudeContent<'a>>,
+}
+
+/// A content block in the format of Anthropic's vision API.
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClaudeContent<'a> {
+    Text { text: &'a str },
+    Image { source: ClaudeImageSource<'a> },
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClaudeImageSource<'a> {
+    Base64 { media_type: &'a str, data: &'a str },
+    Url { url: &'a str },
+}
+
+impl<'a> From<&'a Message> for ClaudeMessage<'a> {
+    fn from(message: &'a Message) -> Self {
+        let content = message
+            .content
+            .iter()
+            .map(|part| match part {
+                Content::Text(text) => ClaudeContent::Text { text },
+                Content::Image { source: ImageSource::Base64(data), media_type } => ClaudeContent::Image {
+                    source: ClaudeImageSource::Base64 { media_type, data },
+                },
+                Content::Image { source: ImageSource::Url(url), .. } => ClaudeContent::Image {
+                    source: ClaudeImageSource::Url { url },
+                },
+            })
+            .collect();
+        Self { role: &message.role, content }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct NonStreamingResponse {
+    content: Vec<ContentBlock>,
+    usage: Usage,
+    stop_reason: Option<String>,
+    stop_sequence: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ContentBlock {
+    text: String,
+}
+
+impl NonStreamingResponse {
+    pub(crate) fn into_llm_response(self, latency_ms: u64) -> LLMResponse {
+        let content = self
+            .content
+            .first()
+            .map_or(String::new(), |c| c.text.clone());
+
+        // Populate the full LLMResponse, including token usage
+        LLMResponse {
+            content,
+            input_tokens: self.usage.input_tokens,
+            output_tokens: self.usage.output_tokens,
+            stop_reason: self.stop_reason.unwrap_or_default(),
+            stop_sequence: self.stop_sequence,
+            latency_ms,
+            cache_read_tokens: self.usage.cache_read_input_tokens,
+            truncated: false,
+        }
+    }
+}
+
+// --- Streaming ---
+
+/// The server-sent events of a streamed response that carry text, usage, or an
+/// error; pings and content block boundaries are ignored.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamEvent {
+    MessageStart { message: StreamStart },
+    ContentBlockDelta { delta: StreamDelta },
+    MessageDelta { delta: StreamStop, usage: StreamUsage },
+    MessageStop,
+    Error { error: serde_json::Value },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize, Debug)]
+struct StreamStart {
+    usage: Usage,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamDelta {
+    TextDelta { text: String },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize, Debug)]
+struct StreamStop {
+    stop_reason: Option<String>,
+    stop_sequence: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct StreamUsage {
+    output_tokens: u32,
+}
+
+/// A streamed response as far as it has arrived.
+#[derive(Default)]
+struct StreamAccumulator {
+    content: String,
+    input_tokens: u32,
+    output_tokens: Option<u32>,
+    cache_read_tokens: Option<u32>,
+    stop_reason: Option<String>,
+    stop_sequence: Option<String>,
+    complete: bool,
+}
+
+impl StreamAccumulator {
+    /// Applies one SSE event block (`event:` and `data:` lines).
+    fn apply(&mut self, block: &str) -> Result<()> {
+        let data: Vec<&str> = block
+            .lines()
+            .filter_map(|line| line.strip_prefix("data:"))
+            .map(str::trim_start)
+            .collect();
+        if data.is_empty() {
+            return Ok(());
+        }
+        let event: StreamEvent = serde_json::from_str(&data.join("\n")).context("Failed to parse stream event")?;
+        match event {
+            StreamEvent::MessageStart { message } => {
+                self.input_tokens = message.usage.input_tokens;
+                self.cache_read_tokens = message.usage.cache_read_input_tokens;
+            }
+            StreamEvent::ContentBlockDelta { delta: StreamDelta::TextDelta { text } } => self.content.push_str(&text),
+            StreamEvent::MessageDelta { delta, usage } => {
+                self.stop_reason = delta.stop_reason;
+                self.stop_sequence = delta.stop_sequence;
+                self.output_tokens = Some(usage.output_tokens);
+            }
+            StreamEvent::MessageStop => self.complete = true,
+            StreamEvent::Error { error } => anyhow::bail!("Claude API stream error: {}", error),
+            StreamEvent::ContentBlockDelta { .. } | StreamEvent::Other => {}
+        }
+        Ok(())
+    }
+
+    /// The response so far. Output tokens are estimated when the stream broke
+    /// before the final usage arrived.
+    fn into_response(self, latency_ms: u64) -> LLMResponse {
+        let truncated = !self.complete;
+        LLMResponse {
+            output_tokens: self.output_tokens.unwrap_or_else(|| CharHeuristicEstimator.estimate_text(&self.content)),
+            content: self.content,
+            input_tokens: self.input_tokens,
+            stop_reason: self.stop_reason.unwrap_or_else(|| if truncated { "truncated".to_string() } else { String::new() }),
+            stop_sequence: self.stop_sequence,
+            latency_ms,
+            cache_read_tokens: self.cache_read_tokens,
+            truncated,
+        }
+    }
+}
+
+/// Host of the first-party Messages API, which rejects fields it doesn't document.
+const ANTHROPIC_API_HOST: &str = "api.anthropic.com";
+
+/// Sampling settings the Messages API has no parameter for.
+pub(crate) const UNSENT_SAMPLING: &[&str] = &["frequency_penalty", "presence_penalty"];
+
+/// Whether `base_url` is the first-party Anthropic API rather than a gateway.
+pub(crate) fn is_anthropic_api(base_url: &str) -> bool {
+    reqwest::Url::parse(base_url).is_ok_and(|url| url.host_str() == Some(ANTHROPIC_API_HOST))
+}
+
+pub(crate) fn build_request<'a>(config: &'a AgentConfig, request: &'a LLMRequest) -> ClaudeRequest<'a> {
+    ClaudeRequest {
+        model: request.model.clone().unwrap_or_else(|| config.model.clone()),
+        max_tokens: config.max_tokens,
+        temperature: config.top_p.is_none().then_some(config.temperature),
+        system: &request.system_prompt,
+        messages: request.messages.iter().map(ClaudeMessage::from).collect(),
+        top_p: config.top_p,
+        top_k: config.top_k,
+        seed: config.seed.filter(|_| !is_anthropic_api(&config.api_base_url)),
+        stop_sequences: &config.stop_sequences,
+        output_format: output_format(&config.response_format),
+        stream: false, // Core primitive is non-streaming for agentic work
+    }
+}
+
+/// The Messages API `output_format` for `format`: a JSON Schema for
+/// structured outputs, or `json_object` for gateways and endpoints that accept
+/// it (config validation rejects it for api.anthropic.com).
+fn output_format(format: &ResponseFormat) -> Option<serde_json::Value> {
+    match format {
+        ResponseFormat::Text => None,
+        ResponseFormat::JsonObject => Some(serde_json::json!({ "type": "json_object" })),
+        ResponseFormat::JsonSchema(schema) => Some(serde_json::json!({ "type": "json_schema", "schema": schema })),
+    }
+}
+
+/// The JSON body `ClaudeProvider` would POST to `/v1/messages` for `request`, pretty-printed.
+pub fn request_json(config: &AgentConfig, request: &LLMRequest) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&build_request(config, request))?)
+}
+
+// --- Claude Provider (Refactored from ClaudeClient) ---
+
+/// A stateless provider for interacting with the Claude API. With several
+/// keys (`key_file_paths`), a rate-limited request is retried with the next.
+pub struct ClaudeProvider {
+    client: Client,
+    /// Client for streamed requests, which only time out while idle.
+    stream_client: Client,
+    config: AgentConfig,
+    api_keys: Vec<String>,
+    /// Index into `api_keys` of the key requests are sent with.
+    active_key: AtomicUsize,
+    streaming: bool,
+}
+
+impl ClaudeProvider {
+    pub async fn new(config: AgentConfig) -> Result<Self> {
+        config.validate_sampling(CLAUDE_MAX_TEMPERATURE)?;
+        config.warn_unsent_sampling("Messages API", UNSENT_SAMPLING);
+        if config.seed.is_some() && is_anthropic_api(&config.api_base_url) {
+            tracing::warn!("The Anthropic API doesn't accept a seed; it won't be sent");
+        }
+        let api_keys = keystore::read_api_keys(&config.key_files()).await?;
+
+        let client = http::build_client(&config)?;
+        let stream_client = http::build_streaming_client(&config)?;
+
+        Ok(Self {
+            client,
+            stream_client,
+            config,
+            api_keys,
+            active_key: AtomicUsize::new(0),
+            streaming: false,
+        })
+    }
+
+    fn api_key(&self) -> &str {
+        &self.api_keys[self.active_key.load(Ordering::Relaxed)]
+    }
+
+    /// Streams responses, so a timeout or dropped connection partway through
+    /// returns the text received so far (flagged `truncated`) instead of an error.
+    pub fn with_streaming(mut self, streaming: bool) -> Self {
+        self.streaming = streaming;
+        self
+    }
+
+    /// Maps a generic request onto the Messages API body.
+    fn build_request<'a>(&'a self, request: &'a LLMRequest) -> ClaudeRequest<'a> {
+        build_request(&self.config, request)
+    }
+
+    /// Starts a request to `url` with the authentication, version, and configured extra headers set.
+    fn api_request(&self, method: reqwest::Method, url: &str) -> reqwest::RequestBuilder {
+        self.authorized(self.client.request(method, url))
+    }
+
+    /// Sets the authentication, version, and configured extra headers on `builder`.
+    fn authorized(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        let mut builder = builder
+            .header("x-api-key", self.api_key())
+            .header("anthropic-version", &self.config.api_version)
+            .header("content-type", "application/json");
+        if !self.config.beta_features.is_empty() {
+            builder = builder.header("anthropic-beta", self.config.beta_features.join(","));
+        }
+        for (name, value) in &self.config.extra_headers {
+            builder = builder.header(name, value);
+        }
+        builder
+    }
+
+    fn transport_error(&self, e: reqwest::Error, what: &'static str) -> anyhow::Error {
+        http::transport_error(&self.config, e, what)
+    }
+
+    /// Sends the request `build` makes with the active key. On a 429, moves
+    /// to the next key and sends it again, until every key has been tried.
+    async fn send(&self, build: impl Fn() -> reqwest::RequestBuilder, what: &'static str) -> Result<reqwest::Response> {
+        let mut attempts = 1;
+        loop {
+            let key_index = self.active_key.load(Ordering::Relaxed);
+            if self.api_keys.len() > 1 {
+                tracing::debug!(key_index, keys = self.api_keys.len(), "Using API key");
+            }
+            let response = build().send().await.map_err(|e| self.transport_error(e, what))?;
+            if response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS || attempts >= self.api_keys.len() {
+                return Ok(response);
+            }
+            let next = (key_index + 1) % self.api_keys.len();
+            // A concurrent request may have moved on already.
+            let _ = self.active_key.compare_exchange(key_index, next, Ordering::Relaxed, Ordering::Relaxed);
+            tracing::debug!(key_index, next_key_index = next, "API key rate limited; switching keys");
+            attempts += 1;
+        }
+    }
+
+    /// Sends `request` without streaming and returns the response body as
+    /// received, unparsed, with the error its status maps to, if any, so the
+    /// body of a failure can be shown too.
+    pub async fn invoke_raw(&self, request: &LLMRequest) -> Result<(String, Option<ApiError>)> {
+        let claude_request = self.build_request(request);
+        let url = format!("{}/v1/messages", self.config.api_base_url);
+        http::trace_body(&url, &claude_request, self.api_key());
+        let response = self
+            .send(|| self.api_request(reqwest::Method::POST, &url).json(&claude_request), "Failed to send request to Claude API")
+            .await?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| self.transport_error(e, "Failed to read the Claude API response"))?;
+        let error = (!status.is_success()).then(|| ApiError::from_response(status, &headers, body.clone()));
+        Ok((body, error))
+    }
+
+    /// Submits `(custom_id, request)` pairs to the Messages Batches API for
+    /// asynchronous processing at reduced cost. Returns the batch id.
+    pub async fn submit_batch(&self, requests: Vec<(String, LLMRequest)>) -> Result<String> {
+        let body = BatchCreateRequest {
+            requests: requests
+                .iter()
+                .map(|(custom_id, request)| BatchRequestItem {
+                    custom_id,
+                    params: self.build_request(request),
+                })
+                .collect(),
+        };
+        let url = format!("{}/v1/messages/batches", self.config.api_base_url);
+        let response = self
+            .send(|| self.api_request(reqwest::Method::POST, &url).json(&body), "Failed to submit message batch")
+            .await?;
+        let status: BatchStatus = http::check_status(response)
+            .await?
+            .json()
+            .await
+            .context("Failed to parse batch creation response")?;
+        Ok(status.id)
+    }
+
+    /// Fetches the current processing state of a batch.
+    pub async fn poll_batch(&self, batch_id: &str) -> Result<BatchStatus> {
+        let url = format!("{}/v1/messages/batches/{}", self.config.api_base_url, batch_id);
+        let response = self
+            .send(|| self.api_request(reqwest::Method::GET, &url), "Failed to poll message batch")
+            .await?;
+        http::check_status(response)
+            .await?
+            .json()
+            .await
+            .context("Failed to parse batch status")
+    }
+
+    /// Downloads the results of an ended batch. Individual requests that
+    /// errored, were canceled, or expired are reported as `Err` entries.
+    pub async fn download_batch_results(&self, batch_id: &str) -> Result<Vec<(String, Result<LLMResponse>)>> {
+        let status = self.poll_batch(batch_id).await?;
+        let Some(results_url) = status.results_url else {
+            anyhow::bail!("Batch {} has no results yet (status: {})", batch_id, status.processing_status);
+        };
+        let response = self
+            .send(|| self.api_request(reqwest::Method::GET, &results_url), "Failed to download batch results")
+            .await?;
+        let body = http::check_status(response).await?.text().await?;
+
+        body.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let parsed: BatchResultLine =
+                    serde_json::from_str(line).context("Failed to parse batch result line")?;
+                let result = match parsed.result {
+                    // Batch results carry no per-request timing.
+                    BatchResultBody::Succeeded { message } => Ok(message.into_llm_response(0)),
+                    BatchResultBody::Errored { error } => Err(anyhow::anyhow!("Request errored: {}", error)),
+                    BatchResultBody::Canceled => Err(anyhow::anyhow!("Request was canceled")),
+                    BatchResultBody::Expired => Err(anyhow::anyhow!("Request expired before processing")),
+                };
+                Ok((parsed.custom_id, result))
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl LLM for ClaudeProvider {
+    async fn invoke(&self, request: &LLMRequest) -> Result<LLMResponse> {
+        llm::validate_messages(&request.messages)?;
+        let mut claude_request = self.build_request(request);
+        claude_request.stream = self.streaming;
+
+        let url = format!("{}/v1/messages", self.config.api_base_url);
+        http::trace_body(&url, &claude_request, self.api_key());
+        let started = Instant::now();
+
+        if self.streaming {
+            // No total timeout, so a long generation isn't cut off; only a
+            // stream that goes quiet for `idle` fails.
+            let idle = Duration::from_secs(self.config.stream_idle_timeout_secs);
+            let send = self.send(
+                || self.authorized(self.stream_client.post(&url)).json(&claude_request),
+                "Failed to send request to Claude API",
+            );
+            let response = tokio::time::timeout(idle, send).await.map_err(|_| ApiError::StreamStalled(idle))??;
+            let mut response = http::check_status(response).await?;
+            let mut stream = StreamAccumulator::default();
+            // Bytes, not text, since a chunk can end partway through a character.
+            let mut buffer: Vec<u8> = Vec::new();
+            while !stream.complete {
+                let Ok(chunk) = tokio::time::timeout(idle, response.chunk()).await else {
+                    if stream.content.is_empty() {
+                        return Err(ApiError::StreamStalled(idle).into());
+                    }
+                    tracing::warn!("Stream sent nothing for {}s, keeping the partial response", idle.as_secs());
+                    break;
+                };
+                match chunk {
+                    Ok(Some(bytes)) => {
+                        buffer.extend_from_slice(&bytes);
+                        while let Some(end) = buffer.windows(2).position(|pair| pair == b"\n\n") {
+                            let block: Vec<u8> = buffer.drain(..end + 2).collect();
+                            stream.apply(&String::from_utf8_lossy(&block))?;
+                        }
+                    }
+                    Ok(None) => break,
+                    // Nothing worth keeping yet, so this is an ordinary failure.
+                    Err(e) if stream.content.is_empty() => return Err(self.transport_error(e, "Claude API stream failed")),
+                    Err(e) => {
+                        tracing::warn!("Stream interrupted, keeping the partial response: {}", e);
+                        break;
+                    }
+                }
+            }
+            if !stream.complete && stream.content.is_empty() {
+                anyhow::bail!("Claude API stream ended before any text arrived");
+            }
+            return Ok(stream.into_response(started.elapsed().as_millis() as u64));
+        }
+
+        let response = self
+            .send(|| self.api_request(reqwest::Method::POST, &url).json(&claude_request), "Failed to send request to Claude API")
+            .await?;
+        let parsed_response: NonStreamingResponse = http::check_status(response)
+            .await?
+            .json()
+            .await
+            .map_err(|e| self.transport_error(e, "Failed to parse non-streaming response"))?;
+
+        Ok(parsed_response.into_llm_response(started.elapsed().as_millis() as u64))
+    }
+
+    /// Every page of `GET /v1/models`, newest models first.
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let url = format!("{}/v1/models", self.config.api_base_url);
+        let mut models = Vec::new();
+        let mut after_id: Option<String> = None;
+        loop {
+            let page_request = || {
+                let builder = self.api_request(reqwest::Method::GET, &url).query(&[("limit", "1000")]);
+                match &after_id {
+                    Some(after_id) => builder.query(&[("after_id", after_id)]),
+                    None => builder,
+                }
+            };
+            let response = self.send(page_request, "Failed to list models").await?;
+            let page: ModelsPage = http::check_status(response)
+                .await?
+                .json()
+                .await
+                .map_err(|e| self.transport_error(e, "Failed to parse model list"))?;
+            models.extend(page.data);
+            match page.last_id {
+                Some(last_id) if page.has_more => after_id = Some(last_id),
+                _ => return Ok(models),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::test_support::{key_file, mock_event_stream, mock_slow_event_stream};
+
+    #[test]
+    fn seed_is_only_sent_to_gateways() {
+        let request = LLMRequest { system_prompt: "s".to_string(), messages: vec![Message::user("hi")], model: None };
+        let config = AgentConfig { seed: Some(7), temperature: 0.0, ..AgentConfig::default() };
+        assert!(!request_json(&config, &request).unwrap().contains("\"seed\""));
+        let gateway = AgentConfig { api_base_url: "https://gateway.example.com/anthropic".to_string(), ..config };
+        assert!(request_json(&gateway, &request).unwrap().contains("\"seed\": 7"));
+    }
+
+    #[test]
+    fn top_p_replaces_temperature() {
+        let request = LLMRequest { system_prompt: "s".to_string(), messages: vec![Message::user("hi")], model: None };
+        let config = AgentConfig { temperature: 0.2, ..AgentConfig::default() };
+        assert!(request_json(&config, &request).unwrap().contains("\"temperature\": 0.2"));
+        let nucleus = AgentConfig { top_p: Some(0.9), ..config };
+        let json = request_json(&nucleus, &request).unwrap();
+        assert!(json.contains("\"top_p\": 0.9") && !json.contains("temperature"), "{}", json);
+    }
+
+    #[tokio::test]
+    async fn keeps_the_partial_text_of_a_dropped_stream() {
+        let key_file = key_file("sk-test\n");
+        let start = "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":12,\"output_tokens\":1}}}\n\n";
+        let deltas = "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Once upon \"}}\n\nevent: ping\ndata: {\"type\":\"ping\"}\n\nevent: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"a time\"}}\n\n";
+        let end = "event: message_delta\ndata: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\",\"stop_sequence\":null},\"usage\":{\"output_tokens\":4}}\n\nevent: message_stop\ndata: {\"type\":\"message_stop\"}\n\n";
+        let (url, server) = mock_event_stream(vec![("200 OK", format!("{}{}{}", start, deltas, end)), ("200 OK", format!("{}{}", start, deltas))]).await;
+
+        let config = AgentConfig { api_base_url: url, key_file_path: key_file.path().to_path_buf(), ..AgentConfig::default() };
+        let provider = ClaudeProvider::new(config).await.unwrap().with_streaming(true);
+        let request = LLMRequest { system_prompt: String::new(), messages: vec![Message::user("Tell me a story")], model: None };
+        let full = provider.invoke(&request).await.unwrap();
+        let partial = provider.invoke(&request).await.unwrap();
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].contains(r#""stream":true"#), "{}", requests[0]);
+        assert_eq!((full.content.as_str(), full.output_tokens, full.truncated), ("Once upon a time", 4, false));
+        assert_eq!(full.stop_reason, "end_turn");
+        assert_eq!((partial.content.as_str(), partial.input_tokens, partial.truncated), ("Once upon a time", 12, true));
+        assert_eq!(partial.stop_reason, "truncated");
+        assert!(partial.output_tokens > 0);
+    }
+
+    #[tokio::test]
+    async fn a_slow_stream_outlasts_the_request_timeout() {
+        let key_file = key_file("sk-test\n");
+        let delta = |text: &str| format!("event: content_block_delta\ndata: {{\"type\":\"content_block_delta\",\"index\":0,\"delta\":{{\"type\":\"text_delta\",\"text\":\"{}\"}}}}\n\n", text);
+        let start = "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":5,\"output_tokens\":1}}}\n\n";
+        let end = "event: message_delta\ndata: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\"},\"usage\":{\"output_tokens\":3}}\n\nevent: message_stop\ndata: {\"type\":\"message_stop\"}\n\n";
+        let events = vec![start.to_string(), delta("slow "), delta("but "), delta("steady"), end.to_string()];
+        let (url, _server) = mock_slow_event_stream(events, Duration::from_millis(400)).await;
+
+        // 1.6s of pauses against a 1s request timeout; no pause reaches the idle timeout.
+        let config = AgentConfig { api_base_url: url, key_file_path: key_file.path().to_path_buf(), request_timeout_secs: 1, stream_idle_timeout_secs: 2, ..AgentConfig::default() };
+        let provider = ClaudeProvider::new(config).await.unwrap().with_streaming(true);
+        let request = LLMRequest { system_prompt: String::new(), messages: vec![Message::user("Take your time")], model: None };
+        let response = provider.invoke(&request).await.unwrap();
+
+        assert_eq!((response.content.as_str(), response.truncated), ("slow but steady", false));
+        assert_eq!(response.stop_reason, "end_turn");
+    }
+
+    #[tokio::test]
+    async fn a_stalled_stream_hits_the_idle_timeout() {
+        let key_file = key_file("sk-test\n");
+        let start = "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":5,\"output_tokens\":1}}}\n\n";
+        let (url, _server) = mock_slow_event_stream(vec![start.to_string(), start.to_string()], Duration::from_secs(3)).await;
+
+        let config = AgentConfig { api_base_url: url, key_file_path: key_file.path().to_path_buf(), stream_idle_timeout_secs: 1, ..AgentConfig::default() };
+        let provider = ClaudeProvider::new(config).await.unwrap().with_streaming(true);
+        let request = LLMRequest { system_prompt: String::new(), messages: vec![Message::user("Hello?")], model: None };
+        let error = provider.invoke(&request).await.unwrap_err();
+
+        assert_eq!(error.downcast_ref::<ApiError>(), Some(&ApiError::StreamStalled(Duration::from_secs(1))));
+    }
+
+    #[tokio::test]
+    async fn sends_stop_sequences_and_reports_the_one_hit() {
+        let key_file = key_file("sk-test\n");
+        let stopped = r#"{"content":[{"type":"text","text":"Done"}],"stop_reason":"stop_sequence","stop_sequence":"END","usage":{"input_tokens":3,"output_tokens":1}}"#;
+        let (url, server) = mock_event_stream(vec![("200 OK", stopped.to_string()), ("200 OK", stopped.to_string())]).await;
+
+        let stop_sequences = vec!["END".to_string(), "\n\nUser:".to_string()];
+        let config = AgentConfig {
+            api_base_url: url,
+            key_file_path: key_file.path().to_path_buf(),
+            stop_sequences,
+            top_k: Some(40),
+            response_format: ResponseFormat::JsonSchema(serde_json::json!({ "type": "object" })),
+            ..AgentConfig::default()
+        };
+        let request = LLMRequest { system_prompt: String::new(), messages: vec![Message::user("ping")], model: None };
+        let response = ClaudeProvider::new(config.clone()).await.unwrap().invoke(&request).await.unwrap();
+        let unset = AgentConfig { stop_sequences: Vec::new(), response_format: ResponseFormat::Text, ..config };
+        ClaudeProvider::new(unset).await.unwrap().invoke(&request).await.unwrap();
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].contains(r#""stop_sequences":["END","\n\nUser:"]"#), "{}", requests[0]);
+        assert!(requests[0].contains(r#""top_k":40"#) && !requests[0].contains("top_p"), "{}", requests[0]);
+        assert!(requests[0].contains(r#""output_format":{"schema":{"type":"object"},"type":"json_schema"}"#), "{}", requests[0]);
+        assert!(!requests[1].contains("stop_sequences") && !requests[1].contains("output_format"), "{}", requests[1]);
+        assert_eq!(response.stop_reason, "stop_sequence");
+        assert_eq!(response.stop_sequence.as_deref(), Some("END"));
+    }
+
+    #[tokio::test]
+    async fn switches_keys_when_one_is_rate_limited() {
+        let keys = [key_file("sk-first"), key_file("sk-second")];
+        let key_files = keys.iter().map(|key| key.path().to_path_buf()).collect();
+        let ok = r#"{"content":[{"type":"text","text":"pong"}],"stop_reason":"end_turn","stop_sequence":null,"usage":{"input_tokens":3,"output_tokens":1}}"#;
+        let limited = r#"{"type":"error","error":{"type":"rate_limit_error","message":"slow down"}}"#;
+        let (url, server) = mock_event_stream(vec![
+            ("429 Too Many Requests", limited.to_string()),
+            ("200 OK", ok.to_string()),
+            ("200 OK", ok.to_string()),
+            ("429 Too Many Requests", limited.to_string()),
+            ("429 Too Many Requests", limited.to_string()),
+        ])
+        .await;
+
+        let config = AgentConfig { api_base_url: url, key_file_paths: key_files, ..AgentConfig::default() };
+        let provider = ClaudeProvider::new(config).await.unwrap();
+        let request = LLMRequest { system_prompt: String::new(), messages: vec![Message::user("ping")], model: None };
+        let first = provider.invoke(&request).await.unwrap();
+        provider.invoke(&request).await.unwrap();
+        let exhausted = provider.invoke(&request).await.unwrap_err();
+
+        let keys: Vec<_> = server
+            .await
+            .unwrap()
+            .iter()
+            .map(|raw| raw.lines().find_map(|line| line.strip_prefix("x-api-key: ")).unwrap().to_string())
+            .collect();
+        assert_eq!(first.content, "pong");
+        // The second key stays active until it is rate limited too.
+        assert_eq!(keys, ["sk-first", "sk-second", "sk-second", "sk-second", "sk-first"]);
+        assert!(matches!(exhausted.downcast_ref::<ApiError>(), Some(ApiError::RateLimited { .. })), "{:#}", exhausted);
+    }
+
+    #[tokio::test]
+    async fn raw_bodies_come_back_unparsed() {
+        let key_file = key_file("sk-test\n");
+        let unexpected = r#"{"content":[{"type":"thinking","thinking":"hmm"}],"brand_new_field":1}"#;
+        let rejected = r#"{"type":"error","error":{"type":"invalid_request_error","message":"bad"}}"#;
+        let (url, server) = mock_event_stream(vec![("200 OK", unexpected.to_string()), ("400 Bad Request", rejected.to_string())]).await;
+
+        let config = AgentConfig { api_base_url: url, key_file_path: key_file.path().to_path_buf(), ..AgentConfig::default() };
+        let provider = ClaudeProvider::new(config).await.unwrap();
+        let request = LLMRequest { system_prompt: String::new(), messages: vec![Message::user("ping")], model: None };
+        let (body, error) = provider.invoke_raw(&request).await.unwrap();
+        let (error_body, bad_request) = provider.invoke_raw(&request).await.unwrap();
+        server.await.unwrap();
+
+        assert_eq!((body.as_str(), error), (unexpected, None));
+        assert_eq!(error_body, rejected);
+        assert_eq!(bad_request, Some(ApiError::BadRequest(rejected.to_string())));
+    }
+}
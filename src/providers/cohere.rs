@@ -0,0 +1,253 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Instant;
+
+use crate::config::{AgentConfig, OPTIONAL_SAMPLING};
+use crate::llm::{LLMRequest, LLMResponse, Message, LLM};
+use crate::providers::http;
+
+/// Cohere's API root; chat requests go to `{base}/v1/chat`.
+pub const COHERE_API_BASE: &str = "https://api.cohere.com";
+
+pub const COMMAND_R_PLUS: &str = "command-r-plus";
+
+// --- API Data Structures ---
+
+/// A data source Cohere can ground answers in, e.g. `{"id": "web-search"}`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CohereConnector {
+    pub id: String,
+    /// Connector-specific settings, passed through unchanged.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<serde_json::Value>,
+}
+
+impl CohereConnector {
+    /// Cohere's built-in web search connector.
+    pub fn web_search() -> Self {
+        Self { id: "web-search".to_string(), options: None }
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct CohereRequest<'a> {
+    model: &'a str,
+    /// The latest user turn; everything before it goes in `chat_history`.
+    message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    chat_history: Vec<CohereHistoryEntry>,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    preamble: &'a str,
+    max_tokens: u32,
+    temperature: f32,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    stop_sequences: &'a [String],
+    #[serde(skip_serializing_if = "<[CohereConnector]>::is_empty")]
+    connectors: &'a [CohereConnector],
+}
+
+#[derive(Serialize, Debug)]
+struct CohereHistoryEntry {
+    role: &'static str,
+    message: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct CohereResponse {
+    text: String,
+    finish_reason: Option<String>,
+    meta: Option<CohereMeta>,
+}
+
+#[derive(Deserialize, Debug)]
+struct CohereMeta {
+    tokens: Option<CohereTokens>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct CohereTokens {
+    #[serde(default)]
+    input_tokens: f64,
+    #[serde(default)]
+    output_tokens: f64,
+}
+
+impl CohereResponse {
+    fn into_llm_response(self, latency_ms: u64) -> LLMResponse {
+        let tokens = self.meta.and_then(|meta| meta.tokens).unwrap_or_default();
+        // Translate to the Anthropic stop reasons the rest of the crate expects.
+        let stop_reason = match self.finish_reason.as_deref().unwrap_or_default() {
+            "COMPLETE" => "end_turn",
+            "MAX_TOKENS" => "max_tokens",
+            "STOP_SEQUENCE" => "stop_sequence",
+            other => other,
+        }
+        .to_string();
+        LLMResponse {
+            content: self.text,
+            // Cohere reports token counts as floats.
+            input_tokens: tokens.input_tokens as u32,
+            output_tokens: tokens.output_tokens as u32,
+            stop_reason,
+            stop_sequence: None,
+            latency_ms,
+            cache_read_tokens: None,
+            truncated: false,
+        }
+    }
+}
+
+/// Maps a message role onto Cohere's `USER`/`CHATBOT` history roles.
+fn history_role(message: &Message) -> &'static str {
+    if message.role == "assistant" {
+        "CHATBOT"
+    } else {
+        "USER"
+    }
+}
+
+/// Splits `request` into Cohere's current `message` and preceding `chat_history`.
+fn build_request<'a>(
+    config: &'a AgentConfig,
+    connectors: &'a [CohereConnector],
+    request: &'a LLMRequest,
+) -> Result<CohereRequest<'a>> {
+    let Some((last, history)) = request.messages.split_last() else {
+        anyhow::bail!("Cohere requests need at least one message");
+    };
+    if last.role != "user" {
+        anyhow::bail!("Cohere requests must end with a user message, not {:?}", last.role);
+    }
+    Ok(CohereRequest {
+        model: request.model.as_deref().unwrap_or(&config.model),
+        // Cohere's chat endpoint takes text only, so image parts are dropped.
+        message: last.text_content(),
+        chat_history: history
+            .iter()
+            .map(|message| CohereHistoryEntry { role: history_role(message), message: message.text_content() })
+            .collect(),
+        preamble: &request.system_prompt,
+        max_tokens: config.max_tokens,
+        temperature: config.temperature,
+        stop_sequences: &config.stop_sequences,
+        connectors,
+    })
+}
+
+// --- Cohere Provider ---
+
+/// A provider for Cohere's Command models such as `command-r-plus`.
+pub struct CohereProvider {
+    client: Client,
+    config: AgentConfig,
+    api_key: String,
+    base_url: String,
+    connectors: Vec<CohereConnector>,
+}
+
+impl CohereProvider {
+    /// Creates a provider for `config.model` using the Cohere key stored at `key_file_path`.
+    pub async fn new(config: AgentConfig, key_file_path: &Path) -> Result<Self> {
+        config.warn_unsent_sampling("Cohere", OPTIONAL_SAMPLING);
+        let api_key = tokio::fs::read_to_string(key_file_path)
+            .await
+            .with_context(|| format!("Failed to read Cohere API key from {}", key_file_path.display()))?;
+        Ok(Self {
+            client: http::build_client(&config)?,
+            config,
+            api_key: api_key.trim().to_string(),
+            base_url: COHERE_API_BASE.to_string(),
+            connectors: Vec::new(),
+        })
+    }
+
+    /// Grounds answers in these connectors, e.g. `CohereConnector::web_search()`.
+    pub fn with_connectors(mut self, connectors: Vec<CohereConnector>) -> Self {
+        self.connectors = connectors;
+        self
+    }
+
+    /// Points the provider at a different server, e.g. a proxy or a mock.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+}
+
+#[async_trait]
+impl LLM for CohereProvider {
+    async fn invoke(&self, request: &LLMRequest) -> Result<LLMResponse> {
+        let body = build_request(&self.config, &self.connectors, request)?;
+
+        let url = format!("{}/v1/chat", self.base_url);
+        http::trace_body(&url, &body, &self.api_key);
+        let mut builder = self.client.post(url).bearer_auth(&self.api_key);
+        for (name, value) in &self.config.extra_headers {
+            builder = builder.header(name, value);
+        }
+
+        let started = Instant::now();
+        let response = builder
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| http::transport_error(&self.config, e, "Failed to send request to Cohere API"))?;
+
+        let parsed: CohereResponse = http::check_status(response)
+            .await?
+            .json()
+            .await
+            .map_err(|e| http::transport_error(&self.config, e, "Failed to parse Cohere response"))?;
+
+        Ok(parsed.into_llm_response(started.elapsed().as_millis() as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::test_support::{key_file, mock_reply};
+
+    #[tokio::test]
+    async fn splits_history_from_the_latest_message() {
+        let key_file = key_file("co-test\n");
+        let (url, server) = mock_reply(r#"{"text":"pong","finish_reason":"MAX_TOKENS","meta":{"tokens":{"input_tokens":9.0,"output_tokens":2.0}}}"#).await;
+
+        let config = AgentConfig { model: COMMAND_R_PLUS.to_string(), stop_sequences: vec!["END".to_string()], ..AgentConfig::default() };
+        let provider = CohereProvider::new(config, key_file.path())
+            .await
+            .unwrap()
+            .with_base_url(url)
+            .with_connectors(vec![CohereConnector::web_search()]);
+        let response = provider
+            .invoke(&LLMRequest {
+                system_prompt: "Be brief.".to_string(),
+                messages: vec![Message::user("hi"), Message::assistant("hello"), Message::user("ping")],
+                model: None,
+            })
+            .await
+            .unwrap();
+
+        let raw = server.await.unwrap();
+        assert!(raw.starts_with("POST /v1/chat "), "{}", raw);
+        assert!(raw.to_ascii_lowercase().contains("authorization: bearer co-test\r\n"), "{}", raw);
+        assert!(raw.contains(r#""message":"ping","chat_history":[{"role":"USER","message":"hi"},{"role":"CHATBOT","message":"hello"}],"preamble":"Be brief.""#), "{}", raw);
+        assert!(raw.contains(r#""stop_sequences":["END"],"connectors":[{"id":"web-search"}]"#), "{}", raw);
+        assert_eq!(response.content, "pong");
+        assert_eq!((response.input_tokens, response.output_tokens), (9, 2));
+        assert_eq!(response.stop_reason, "max_tokens");
+    }
+
+    #[test]
+    fn requests_must_end_with_a_user_message() {
+        let config = AgentConfig::default();
+        let request = |messages| LLMRequest { system_prompt: String::new(), messages, model: None };
+        let empty = build_request(&config, &[], &request(Vec::new())).unwrap_err();
+        assert!(empty.to_string().contains("at least one message"), "{}", empty);
+        let prefilled = build_request(&config, &[], &request(vec![Message::user("hi"), Message::assistant("{")])).unwrap_err();
+        assert!(prefilled.to_string().contains("end with a user message"), "{}", prefilled);
+    }
+}
@@ -0,0 +1,54 @@
+//! A stand-in provider that shows what would be sent instead of sending it.
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::config::AgentConfig;
+use crate::llm::{LLMRequest, LLMResponse, LLM};
+use crate::providers::claude;
+use crate::tokens::{CharHeuristicEstimator, TokenEstimator};
+
+/// The `stop_reason` of every `DryRunLLM` response.
+pub const DRY_RUN_STOP_REASON: &str = "dry_run";
+
+/// What a dry-run turn leaves in the conversation history in place of the
+/// printed payload, so later payloads don't nest earlier ones.
+pub const DRY_RUN_PLACEHOLDER: &str = "(dry run: no response)";
+
+/// Answers every request with the pretty-printed JSON body `ClaudeProvider`
+/// would send, and the estimated input tokens. Nothing goes over the network
+/// and no API key is needed.
+pub struct DryRunLLM {
+    config: AgentConfig,
+    estimator: Box<dyn TokenEstimator>,
+}
+
+impl DryRunLLM {
+    pub fn new(config: AgentConfig) -> Self {
+        Self {
+            config,
+            estimator: Box::new(CharHeuristicEstimator),
+        }
+    }
+
+    pub fn with_estimator(mut self, estimator: Box<dyn TokenEstimator>) -> Self {
+        self.estimator = estimator;
+        self
+    }
+}
+
+#[async_trait]
+impl LLM for DryRunLLM {
+    async fn invoke(&self, request: &LLMRequest) -> Result<LLMResponse> {
+        Ok(LLMResponse {
+            content: claude::request_json(&self.config, request)?,
+            input_tokens: self.estimator.estimate_request(request),
+            output_tokens: 0,
+            stop_reason: DRY_RUN_STOP_REASON.to_string(),
+            stop_sequence: None,
+            latency_ms: 0,
+            cache_read_tokens: None,
+            truncated: false,
+        })
+    }
+}
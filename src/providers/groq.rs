@@ -0,0 +1,34 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::path::Path;
+
+use crate::config::AgentConfig;
+use crate::llm::{LLMRequest, LLMResponse, ModelInfo, LLM};
+use crate::providers::openai_compat::OpenAICompatibleProvider;
+
+/// Groq's OpenAI-compatible endpoint.
+pub const GROQ_API_BASE: &str = "https://api.groq.com/openai/v1";
+
+/// A provider for Groq's low-latency inference on open models such as
+/// `llama3-70b-8192`. Set `config.model` to a Groq model id.
+pub struct GroqProvider(OpenAICompatibleProvider);
+
+impl GroqProvider {
+    /// Creates a provider using the Groq key stored at `key_file_path`.
+    pub async fn new(config: AgentConfig, key_file_path: &Path) -> Result<Self> {
+        Ok(Self(OpenAICompatibleProvider::new("Groq", config, GROQ_API_BASE, key_file_path).await?))
+    }
+}
+
+#[async_trait]
+impl LLM for GroqProvider {
+    /// Rate limit headers that show the request quota nearly used up are
+    /// reported as `WARN` tracing events.
+    async fn invoke(&self, request: &LLMRequest) -> Result<LLMResponse> {
+        self.0.invoke(request).await
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        self.0.list_models().await
+    }
+}
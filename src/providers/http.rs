@@ -0,0 +1,98 @@
+//! HTTP plumbing shared by every provider: client settings and error mapping.
+
+use anyhow::{Context, Result};
+use reqwest::Client;
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::config::AgentConfig;
+use crate::error::ApiError;
+
+/// A client honoring the configured timeout, proxy, and extra root certificate.
+pub(crate) fn build_client(config: &AgentConfig) -> Result<Client> {
+    client_builder(config, Client::builder().timeout(Duration::from_secs(config.request_timeout_secs)))
+}
+
+/// Like [`build_client`], but the timeout only covers connecting, so a long
+/// stream that keeps sending isn't cut off; callers time out idle reads instead.
+pub(crate) fn build_streaming_client(config: &AgentConfig) -> Result<Client> {
+    client_builder(config, Client::builder().connect_timeout(Duration::from_secs(config.request_timeout_secs)))
+}
+
+fn client_builder(config: &AgentConfig, mut builder: reqwest::ClientBuilder) -> Result<Client> {
+    // Without an explicit proxy, reqwest picks up HTTPS_PROXY/HTTP_PROXY on its own.
+    // Credentials embedded in the URL are used for proxy authentication.
+    if let Some(proxy_url) = &config.proxy_url {
+        let proxy = reqwest::Proxy::all(proxy_url)
+            .context("Invalid proxy URL in proxy_url (credentials not shown)")?;
+        builder = builder.proxy(proxy);
+    }
+    if let Some(ca_cert_path) = &config.ca_cert_path {
+        let pem = std::fs::read(ca_cert_path).with_context(|| {
+            format!("Failed to read ca_cert_path {}", ca_cert_path.display())
+        })?;
+        let certificate = reqwest::Certificate::from_pem(&pem).with_context(|| {
+            format!("ca_cert_path {} is not a valid PEM certificate", ca_cert_path.display())
+        })?;
+        builder = builder.add_root_certificate(certificate);
+    }
+    Ok(builder.build()?)
+}
+
+/// Wraps a transport error, turning timeouts into `ApiError::Timeout` so the
+/// message names the configured limit instead of an opaque reqwest error.
+pub(crate) fn transport_error(config: &AgentConfig, e: reqwest::Error, what: &'static str) -> anyhow::Error {
+    if e.is_timeout() {
+        return ApiError::Timeout(Duration::from_secs(config.request_timeout_secs)).into();
+    }
+    let is_connect = e.is_connect();
+    let error = anyhow::Error::new(e).context(what);
+    if !is_connect {
+        return error;
+    }
+    // Point at the proxy setting, since that's the usual culprit in locked-down networks.
+    if config.proxy_url.is_some() {
+        error.context("Could not connect; check the proxy set in proxy_url")
+    } else if std::env::var_os("HTTPS_PROXY").is_some() || std::env::var_os("https_proxy").is_some() {
+        error.context("Could not connect; check the proxy set in HTTPS_PROXY")
+    } else {
+        error
+    }
+}
+
+/// Logs an outgoing JSON body at `TRACE`, pretty-printed, with `secret` masked wherever it appears.
+pub(crate) fn trace_body(url: &str, body: &impl Serialize, secret: &str) {
+    if !tracing::enabled!(tracing::Level::TRACE) {
+        return;
+    }
+    let mut pretty = serde_json::to_string_pretty(body).unwrap_or_default();
+    if !secret.is_empty() {
+        pretty = pretty.replace(secret, "<redacted>");
+    }
+    tracing::trace!(url, body = pretty.as_str(), "Request body");
+}
+
+/// Response headers worth logging: request IDs and rate-limit counters.
+fn is_diagnostic_header(name: &str) -> bool {
+    matches!(name, "request-id" | "x-request-id" | "retry-after") || name.contains("ratelimit")
+}
+
+/// Turns a non-success status into a typed `ApiError`.
+pub(crate) async fn check_status(response: reqwest::Response) -> Result<reqwest::Response> {
+    let status = response.status();
+    if tracing::enabled!(tracing::Level::DEBUG) {
+        let headers: Vec<String> = response
+            .headers()
+            .iter()
+            .filter(|(name, _)| is_diagnostic_header(name.as_str()))
+            .map(|(name, value)| format!("{}={}", name, value.to_str().unwrap_or("<binary>")))
+            .collect();
+        tracing::debug!(status = status.as_u16(), url = %response.url(), headers = headers.join(" ").as_str(), "HTTP response");
+    }
+    if !status.is_success() {
+        let headers = response.headers().clone();
+        let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+        return Err(ApiError::from_response(status, &headers, error_text).into());
+    }
+    Ok(response)
+}
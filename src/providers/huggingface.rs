@@ -0,0 +1,202 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::config::{AgentConfig, OPTIONAL_SAMPLING};
+use crate::error::ApiError;
+use crate::llm::{LLMRequest, LLMResponse, LLM};
+use crate::providers::http;
+use crate::tokens::{CharHeuristicEstimator, TokenEstimator};
+
+/// The Inference API root; requests go to `{base}/{model}`.
+pub const HUGGINGFACE_API_BASE: &str = "https://api-inference.huggingface.co/models";
+
+/// How many times to wait for a cold model to load before giving up.
+const MAX_LOADING_RETRIES: u32 = 5;
+
+/// Longest single wait for a loading model, whatever `estimated_time` says.
+const MAX_LOADING_WAIT: Duration = Duration::from_secs(60);
+
+// --- API Data Structures ---
+
+#[derive(Serialize, Debug)]
+struct TextGenerationRequest<'a> {
+    inputs: String,
+    parameters: TextGenerationParameters<'a>,
+}
+
+#[derive(Serialize, Debug)]
+struct TextGenerationParameters<'a> {
+    max_new_tokens: u32,
+    temperature: f32,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    stop: &'a [String],
+    /// Only the completion, not the prompt echoed back in front of it.
+    return_full_text: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct GeneratedText {
+    generated_text: String,
+}
+
+/// The 503 body returned while a model is being loaded onto a server.
+#[derive(Deserialize, Debug)]
+struct ModelLoading {
+    estimated_time: f64,
+}
+
+/// Flattens the conversation into one prompt with role labels, ending with an
+/// open assistant turn for the model to complete.
+fn format_prompt(request: &LLMRequest) -> String {
+    let mut prompt = String::new();
+    if !request.system_prompt.is_empty() {
+        prompt.push_str(&format!("System: {}\n\n", request.system_prompt));
+    }
+    for message in &request.messages {
+        let label = if message.role == "assistant" { "Assistant" } else { "User" };
+        // The text-generation task takes text only, so image parts are dropped.
+        prompt.push_str(&format!("{}: {}\n\n", label, message.text_content()));
+    }
+    prompt.push_str("Assistant:");
+    prompt
+}
+
+// --- HuggingFace Provider ---
+
+/// A provider for chat models served by the HuggingFace Inference API's
+/// `text-generation` task. The API reports no token usage, so counts are
+/// estimated locally.
+pub struct HuggingFaceProvider {
+    client: Client,
+    config: AgentConfig,
+    api_key: String,
+    base_url: String,
+}
+
+impl HuggingFaceProvider {
+    /// Creates a provider for the model ID in `config.model` (e.g.
+    /// `mistralai/Mistral-7B-Instruct-v0.3`) using the access token stored at `key_file_path`.
+    pub async fn new(config: AgentConfig, key_file_path: &Path) -> Result<Self> {
+        config.warn_unsent_sampling("HuggingFace", OPTIONAL_SAMPLING);
+        let api_key = tokio::fs::read_to_string(key_file_path)
+            .await
+            .with_context(|| format!("Failed to read HuggingFace token from {}", key_file_path.display()))?;
+        Ok(Self {
+            client: http::build_client(&config)?,
+            config,
+            api_key: api_key.trim().to_string(),
+            base_url: HUGGINGFACE_API_BASE.to_string(),
+        })
+    }
+
+    /// Points the provider at a different server, e.g. a dedicated Inference Endpoint or a mock.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+}
+
+#[async_trait]
+impl LLM for HuggingFaceProvider {
+    async fn invoke(&self, request: &LLMRequest) -> Result<LLMResponse> {
+        let body = TextGenerationRequest {
+            inputs: format_prompt(request),
+            parameters: TextGenerationParameters {
+                max_new_tokens: self.config.max_tokens,
+                temperature: self.config.temperature,
+                stop: &self.config.stop_sequences,
+                return_full_text: false,
+            },
+        };
+        let url = format!("{}/{}", self.base_url, request.model.as_deref().unwrap_or(&self.config.model));
+        http::trace_body(&url, &body, &self.api_key);
+
+        let started = Instant::now();
+        let mut loading_retries = 0;
+        let response = loop {
+            let mut builder = self.client.post(&url).bearer_auth(&self.api_key);
+            for (name, value) in &self.config.extra_headers {
+                builder = builder.header(name, value);
+            }
+            let response = builder
+                .json(&body)
+                .send()
+                .await
+                .map_err(|e| http::transport_error(&self.config, e, "Failed to send request to HuggingFace API"))?;
+            if response.status() != StatusCode::SERVICE_UNAVAILABLE || loading_retries == MAX_LOADING_RETRIES {
+                break http::check_status(response).await?;
+            }
+
+            // A cold model answers 503 with how long loading should take; anything
+            // else on a 503 is a real outage.
+            let headers = response.headers().clone();
+            let text = response.text().await.unwrap_or_default();
+            let Ok(loading) = serde_json::from_str::<ModelLoading>(&text) else {
+                return Err(ApiError::from_response(StatusCode::SERVICE_UNAVAILABLE, &headers, text).into());
+            };
+            let wait = Duration::try_from_secs_f64(loading.estimated_time).unwrap_or_default().min(MAX_LOADING_WAIT);
+            tracing::debug!(model = self.config.model.as_str(), wait_secs = wait.as_secs_f64(), "Model is loading");
+            tokio::time::sleep(wait).await;
+            loading_retries += 1;
+        };
+
+        let generated: Vec<GeneratedText> = response
+            .json()
+            .await
+            .map_err(|e| http::transport_error(&self.config, e, "Failed to parse HuggingFace response"))?;
+        let content = generated.into_iter().next().map(|g| g.generated_text).unwrap_or_default();
+        let content = content.trim_start().to_string();
+
+        let estimator = CharHeuristicEstimator;
+        Ok(LLMResponse {
+            input_tokens: estimator.estimate_text(&body.inputs),
+            output_tokens: estimator.estimate_text(&content),
+            content,
+            // The text-generation task doesn't say why generation stopped.
+            stop_reason: "end_turn".to_string(),
+            stop_sequence: None,
+            latency_ms: started.elapsed().as_millis() as u64,
+            cache_read_tokens: None,
+            truncated: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::Message;
+    use crate::providers::test_support::{key_file, mock_server};
+
+    #[tokio::test]
+    async fn waits_for_a_loading_model_and_estimates_tokens() {
+        let key_file = key_file("hf_test\n");
+        let (url, server) = mock_server(vec![
+            ("503 Service Unavailable", r#"{"error":"Model is currently loading","estimated_time":0.01}"#),
+            ("200 OK", r#"[{"generated_text":" pong"}]"#),
+        ])
+        .await;
+
+        let config = AgentConfig { model: "org/chat-model".to_string(), ..AgentConfig::default() };
+        let provider = HuggingFaceProvider::new(config, key_file.path()).await.unwrap().with_base_url(url);
+        let response = provider
+            .invoke(&LLMRequest { system_prompt: "Be brief.".to_string(), messages: vec![Message::user("ping")], model: None })
+            .await
+            .unwrap();
+
+        let requests = server.await.unwrap();
+        assert_eq!(requests.len(), 2);
+        let raw = &requests[1];
+        assert!(raw.starts_with("POST /org/chat-model "), "{}", raw);
+        assert!(raw.to_ascii_lowercase().contains("authorization: bearer hf_test\r\n"), "{}", raw);
+        assert!(raw.contains(r#""inputs":"System: Be brief.\n\nUser: ping\n\nAssistant:""#), "{}", raw);
+        assert!(raw.contains(r#""return_full_text":false"#), "{}", raw);
+        assert_eq!(response.content, "pong");
+        assert_eq!(response.output_tokens, 1);
+        assert!(response.input_tokens > 0);
+    }
+}
@@ -0,0 +1,109 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::path::Path;
+
+use crate::config::AgentConfig;
+use crate::llm::{LLMRequest, LLMResponse, ModelInfo, LLM};
+use crate::providers::openai_compat::OpenAICompatibleProvider;
+
+/// Mistral's OpenAI-compatible endpoint.
+pub const MISTRAL_API_BASE: &str = "https://api.mistral.ai/v1";
+
+/// Codestral is served from its own endpoint, with its own API key.
+pub const CODESTRAL_API_BASE: &str = "https://codestral.mistral.ai/v1";
+
+pub const MISTRAL_LARGE: &str = "mistral-large-latest";
+pub const MISTRAL_SMALL: &str = "mistral-small-latest";
+pub const CODESTRAL: &str = "codestral-latest";
+
+/// Whether `model` is a Codestral model, which routes to `CODESTRAL_API_BASE`.
+pub fn is_codestral(model: &str) -> bool {
+    model.starts_with("codestral")
+}
+
+/// A provider for Mistral models such as `mistral-large-latest` and `codestral-latest`.
+pub struct MistralProvider(OpenAICompatibleProvider);
+
+impl MistralProvider {
+    /// Creates a provider for `config.model` using the Mistral key stored at
+    /// `key_file_path`. Codestral models use the Codestral endpoint.
+    pub async fn new(config: AgentConfig, key_file_path: &Path) -> Result<Self> {
+        let base_url = if is_codestral(&config.model) { CODESTRAL_API_BASE } else { MISTRAL_API_BASE };
+        Ok(Self(OpenAICompatibleProvider::new("Mistral", config, base_url, key_file_path).await?))
+    }
+
+    /// Sets `safe_prompt`, which makes Mistral prepend its safety system prompt.
+    pub fn with_safe_prompt(self, safe_prompt: bool) -> Self {
+        Self(self.0.with_body_field("safe_prompt", safe_prompt.into()))
+    }
+
+    /// Points the provider at a different server, e.g. a proxy or a mock.
+    pub fn with_base_url(self, base_url: impl Into<String>) -> Self {
+        Self(self.0.with_base_url(base_url))
+    }
+}
+
+#[async_trait]
+impl LLM for MistralProvider {
+    async fn invoke(&self, request: &LLMRequest) -> Result<LLMResponse> {
+        self.0.invoke(request).await
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        self.0.list_models().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{Message, ResponseFormat};
+    use crate::providers::test_support::{key_file, mock_reply};
+
+    #[tokio::test]
+    async fn sends_bearer_token_and_safe_prompt() {
+        let key_file = key_file("test-key\n");
+        let (url, server) = mock_reply(r#"{"choices":[{"message":{"content":"pong"},"finish_reason":"stop"}],"usage":{"prompt_tokens":7,"completion_tokens":1}}"#).await;
+
+        let stop_sequences = ["END", "\n\nUser:", "```", "STOP", "fifth"].map(String::from).to_vec();
+        let config = AgentConfig {
+            model: MISTRAL_LARGE.to_string(),
+            stop_sequences,
+            top_p: Some(0.5),
+            top_k: Some(40),
+            presence_penalty: Some(-1.5),
+            seed: Some(7),
+            response_format: ResponseFormat::JsonObject,
+            ..AgentConfig::default()
+        };
+        let provider = MistralProvider::new(config, key_file.path())
+            .await
+            .unwrap()
+            .with_base_url(url)
+            .with_safe_prompt(true);
+        let response = provider
+            .invoke(&LLMRequest { system_prompt: String::new(), messages: vec![Message::user("ping")], model: None })
+            .await
+            .unwrap();
+
+        let raw = server.await.unwrap();
+        assert!(raw.starts_with("POST /chat/completions "), "{}", raw);
+        assert!(raw.to_ascii_lowercase().contains("authorization: bearer test-key\r\n"), "{}", raw);
+        assert!(raw.contains(r#""safe_prompt":true"#), "{}", raw);
+        assert!(raw.contains(r#""model":"mistral-large-latest""#), "{}", raw);
+        assert!(raw.contains(r#""top_p":0.5,"presence_penalty":-1.5,"seed":7"#), "{}", raw);
+        assert!(!raw.contains("top_k") && !raw.contains("frequency_penalty"), "{}", raw);
+        // Chat Completions takes at most four.
+        assert!(raw.contains(r#""stop":["END","\n\nUser:","```","STOP"]"#), "{}", raw);
+        assert!(raw.contains(r#""response_format":{"type":"json_object"}"#), "{}", raw);
+        assert_eq!(response.content, "pong");
+        assert_eq!((response.input_tokens, response.output_tokens), (7, 1));
+        assert_eq!(response.stop_reason, "end_turn");
+    }
+
+    #[test]
+    fn codestral_models_are_detected() {
+        assert!(is_codestral(CODESTRAL));
+        assert!(!is_codestral(MISTRAL_LARGE));
+    }
+}
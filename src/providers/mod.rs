@@ -0,0 +1,19 @@
+pub mod azure_openai;
+#[cfg(feature = "use-bedrock")]
+mod aws;
+#[cfg(feature = "use-bedrock")]
+pub mod bedrock;
+pub mod claude;
+pub mod cohere;
+pub mod dry_run;
+pub mod groq;
+pub mod huggingface;
+pub mod mistral;
+pub(crate) mod http;
+pub mod openai_compat;
+pub mod perplexity;
+pub mod together;
+#[cfg(test)]
+pub(crate) mod test_support;
+#[cfg(feature = "use-vertex")]
+pub mod vertex;
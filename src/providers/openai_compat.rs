@@ -0,0 +1,374 @@
+//! The OpenAI Chat Completions format, shared by providers whose APIs speak
+//! it (Azure OpenAI, Groq, and other OpenAI-compatible hosts).
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::header::HeaderMap;
+use reqwest::Client;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Instant;
+
+use crate::config::{AgentConfig, OPENAI_MAX_TEMPERATURE};
+use crate::llm::{Content, ImageSource, LLMRequest, LLMResponse, Message, ModelInfo, ResponseFormat, LLM};
+use crate::providers::http;
+
+/// Most stop sequences the Chat Completions `stop` field accepts; the
+/// Messages API allows more.
+pub(crate) const MAX_CHAT_STOP_SEQUENCES: usize = 4;
+
+#[derive(Serialize, Debug)]
+pub(crate) struct ChatRequest<'a> {
+    /// Omitted for deployment-routed APIs, where the URL selects the model.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<&'a str>,
+    pub messages: Vec<ChatMessage<'a>>,
+    pub max_tokens: u32,
+    pub temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    pub stop: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<serde_json::Value>,
+    /// Provider-specific top-level fields, such as Mistral's `safe_prompt`.
+    #[serde(flatten)]
+    pub extra: Option<&'a serde_json::Map<String, serde_json::Value>>,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct ChatMessage<'a> {
+    role: &'a str,
+    content: ChatContent<'a>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(untagged)]
+enum ChatContent<'a> {
+    Text(&'a str),
+    Parts(Vec<ChatPart<'a>>),
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ChatPart<'a> {
+    Text { text: &'a str },
+    ImageUrl { image_url: ImageUrl },
+}
+
+#[derive(Serialize, Debug)]
+struct ImageUrl {
+    url: String,
+}
+
+impl<'a> From<&'a Message> for ChatMessage<'a> {
+    fn from(message: &'a Message) -> Self {
+        // A lone text part goes out as a plain string, which every compatible server accepts.
+        let content = match message.content.as_slice() {
+            [Content::Text(text)] => ChatContent::Text(text),
+            parts => ChatContent::Parts(
+                parts
+                    .iter()
+                    .map(|part| match part {
+                        Content::Text(text) => ChatPart::Text { text },
+                        Content::Image { source: ImageSource::Base64(data), media_type } => ChatPart::ImageUrl {
+                            image_url: ImageUrl { url: format!("data:{};base64,{}", media_type, data) },
+                        },
+                        Content::Image { source: ImageSource::Url(url), .. } => ChatPart::ImageUrl {
+                            image_url: ImageUrl { url: url.clone() },
+                        },
+                    })
+                    .collect(),
+            ),
+        };
+        Self { role: &message.role, content }
+    }
+}
+
+/// Maps a generic request onto the Chat Completions body; the system prompt
+/// becomes the leading `system` message. Stop sequences past the API's
+/// limit are dropped with a warning.
+pub(crate) fn build_request<'a>(
+    config: &'a AgentConfig,
+    model: Option<&'a str>,
+    request: &'a LLMRequest,
+) -> ChatRequest<'a> {
+    let mut messages = Vec::with_capacity(request.messages.len() + 1);
+    if !request.system_prompt.is_empty() {
+        messages.push(ChatMessage {
+            role: "system",
+            content: ChatContent::Text(&request.system_prompt),
+        });
+    }
+    messages.extend(request.messages.iter().map(ChatMessage::from));
+    let stop = &config.stop_sequences[..config.stop_sequences.len().min(MAX_CHAT_STOP_SEQUENCES)];
+    if stop.len() < config.stop_sequences.len() {
+        tracing::warn!(
+            given = config.stop_sequences.len(),
+            sent = stop.len(),
+            "Chat Completions accepts at most {} stop sequences; ignoring the rest",
+            MAX_CHAT_STOP_SEQUENCES
+        );
+    }
+    ChatRequest {
+        model,
+        messages,
+        max_tokens: config.max_tokens,
+        temperature: config.temperature,
+        top_p: config.top_p,
+        frequency_penalty: config.frequency_penalty,
+        presence_penalty: config.presence_penalty,
+        seed: config.seed,
+        stop,
+        response_format: response_format(&config.response_format),
+        extra: None,
+    }
+}
+
+/// Sampling settings Chat Completions has no parameter for.
+pub(crate) const UNSENT_SAMPLING: &[&str] = &["top_k"];
+
+/// The Chat Completions `response_format` for `format`; a schema is sent
+/// strict, so replies are guaranteed to match it. Strict mode only accepts
+/// schemas whose objects set `additionalProperties: false` and list every
+/// property as required; the API rejects any other with a 400.
+fn response_format(format: &ResponseFormat) -> Option<serde_json::Value> {
+    match format {
+        ResponseFormat::Text => None,
+        ResponseFormat::JsonObject => Some(serde_json::json!({ "type": "json_object" })),
+        ResponseFormat::JsonSchema(schema) => Some(serde_json::json!({
+            "type": "json_schema",
+            "json_schema": { "name": "response", "schema": schema, "strict": true },
+        })),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct ChatResponse {
+    choices: Vec<Choice>,
+    #[serde(default)]
+    usage: ChatUsage,
+}
+
+#[derive(Deserialize, Debug)]
+struct Choice {
+    message: ChoiceMessage,
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ChoiceMessage {
+    content: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct ChatUsage {
+    prompt_tokens: u32,
+    completion_tokens: u32,
+    prompt_tokens_details: Option<TokenDetails>,
+    /// Together AI has reported its cache breakdown here instead.
+    completion_tokens_details: Option<TokenDetails>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct TokenDetails {
+    cached_tokens: Option<u32>,
+}
+
+impl ChatUsage {
+    fn cached_tokens(&self) -> Option<u32> {
+        [&self.prompt_tokens_details, &self.completion_tokens_details]
+            .into_iter()
+            .find_map(|details| details.as_ref()?.cached_tokens)
+    }
+}
+
+impl ChatResponse {
+    pub(crate) fn into_llm_response(self, latency_ms: u64) -> LLMResponse {
+        let choice = self.choices.into_iter().next();
+        let finish_reason = choice.as_ref().and_then(|c| c.finish_reason.clone()).unwrap_or_default();
+        // Translate to the Anthropic stop reasons the rest of the crate expects.
+        let stop_reason = match finish_reason.as_str() {
+            "stop" => "end_turn",
+            "length" => "max_tokens",
+            "tool_calls" | "function_call" => "tool_use",
+            other => other,
+        }
+        .to_string();
+        LLMResponse {
+            content: choice.and_then(|c| c.message.content).unwrap_or_default(),
+            input_tokens: self.usage.prompt_tokens,
+            output_tokens: self.usage.completion_tokens,
+            stop_reason,
+            // The Chat Completions API doesn't report which stop sequence matched.
+            stop_sequence: None,
+            latency_ms,
+            cache_read_tokens: self.usage.cached_tokens(),
+            truncated: false,
+        }
+    }
+}
+
+/// The body of `GET {base}/models`: OpenAI's `{"data": [...]}` envelope, or
+/// the bare array Together AI returns.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum ModelList {
+    Envelope { data: Vec<ModelEntry> },
+    Bare(Vec<ModelEntry>),
+}
+
+#[derive(Deserialize, Debug)]
+struct ModelEntry {
+    id: String,
+    display_name: Option<String>,
+    context_length: Option<u32>,
+}
+
+// --- Rate Limits ---
+
+/// Warn once fewer than this fraction of the request quota remains.
+const RATE_LIMIT_WARN_FRACTION: f64 = 0.1;
+
+/// Emits a `WARN` event, with the reset time, when the `x-ratelimit-*`
+/// headers show the request quota is nearly used up.
+fn warn_on_rate_limit(provider: &str, headers: &HeaderMap) {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    let Some(remaining) = header("x-ratelimit-remaining-requests").and_then(|v| v.parse::<u64>().ok()) else {
+        return;
+    };
+    let limit = header("x-ratelimit-limit-requests").and_then(|v| v.parse::<u64>().ok());
+    let low = match limit {
+        Some(limit) => (remaining as f64) < limit as f64 * RATE_LIMIT_WARN_FRACTION,
+        None => remaining == 0,
+    };
+    if low {
+        tracing::warn!(
+            provider,
+            remaining_requests = remaining,
+            limit_requests = ?limit,
+            reset_requests = header("x-ratelimit-reset-requests").unwrap_or("unknown"),
+            "Rate limit nearly exhausted"
+        );
+    }
+}
+
+// --- OpenAI-Compatible Provider ---
+
+/// A provider for any server exposing `POST {base_url}/chat/completions`
+/// with bearer authentication.
+pub struct OpenAICompatibleProvider {
+    client: Client,
+    config: AgentConfig,
+    api_key: String,
+    base_url: String,
+    /// Names the provider in log events.
+    name: &'static str,
+    body_fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl OpenAICompatibleProvider {
+    /// Creates a provider for `base_url` (e.g. `https://api.openai.com/v1`)
+    /// using the key stored at `key_file_path`.
+    pub async fn new(
+        name: &'static str,
+        config: AgentConfig,
+        base_url: impl Into<String>,
+        key_file_path: &Path,
+    ) -> Result<Self> {
+        config.validate_sampling(OPENAI_MAX_TEMPERATURE)?;
+        config.warn_unsent_sampling(name, UNSENT_SAMPLING);
+        let api_key = tokio::fs::read_to_string(key_file_path)
+            .await
+            .with_context(|| format!("Failed to read {} API key from {}", name, key_file_path.display()))?;
+        Ok(Self {
+            client: http::build_client(&config)?,
+            config,
+            api_key: api_key.trim().to_string(),
+            base_url: base_url.into(),
+            name,
+            body_fields: serde_json::Map::new(),
+        })
+    }
+
+    /// Points the provider at a different server, e.g. a proxy or a mock.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Adds a top-level field to every request body, for parameters outside
+    /// the common Chat Completions set.
+    pub fn with_body_field(mut self, name: impl Into<String>, value: serde_json::Value) -> Self {
+        self.body_fields.insert(name.into(), value);
+        self
+    }
+
+    /// Sends `request` to `/chat/completions` and parses the body as `T`, for
+    /// providers whose responses carry more than [`ChatResponse`]. Returns the
+    /// latency in milliseconds alongside it.
+    pub(crate) async fn chat<T: DeserializeOwned>(&self, request: &LLMRequest) -> Result<(T, u64)> {
+        let mut body = build_request(&self.config, Some(request.model.as_deref().unwrap_or(&self.config.model)), request);
+        body.extra = Some(&self.body_fields);
+
+        let url = format!("{}/chat/completions", self.base_url);
+        http::trace_body(&url, &body, &self.api_key);
+        let mut builder = self.client.post(url).bearer_auth(&self.api_key);
+        for (name, value) in &self.config.extra_headers {
+            builder = builder.header(name, value);
+        }
+
+        let started = Instant::now();
+        let response = builder
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| http::transport_error(&self.config, e, "Failed to send chat completions request"))?;
+        warn_on_rate_limit(self.name, response.headers());
+
+        let parsed: T = http::check_status(response)
+            .await?
+            .json()
+            .await
+            .map_err(|e| http::transport_error(&self.config, e, "Failed to parse chat completions response"))?;
+
+        Ok((parsed, started.elapsed().as_millis() as u64))
+    }
+}
+
+#[async_trait]
+impl LLM for OpenAICompatibleProvider {
+    async fn invoke(&self, request: &LLMRequest) -> Result<LLMResponse> {
+        let (parsed, latency_ms) = self.chat::<ChatResponse>(request).await?;
+        Ok(parsed.into_llm_response(latency_ms))
+    }
+
+    /// `GET {base_url}/models`, which OpenAI, Groq, Mistral, and Ollama's `/v1` API all serve.
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let mut builder = self.client.get(format!("{}/models", self.base_url)).bearer_auth(&self.api_key);
+        for (name, value) in &self.config.extra_headers {
+            builder = builder.header(name, value);
+        }
+        let response = builder
+            .send()
+            .await
+            .map_err(|e| http::transport_error(&self.config, e, "Failed to list models"))?;
+        let list: ModelList = http::check_status(response)
+            .await?
+            .json()
+            .await
+            .map_err(|e| http::transport_error(&self.config, e, "Failed to parse model list"))?;
+        let (ModelList::Envelope { data: entries } | ModelList::Bare(entries)) = list;
+        Ok(entries
+            .into_iter()
+            .map(|entry| ModelInfo { id: entry.id, display_name: entry.display_name, context_length: entry.context_length })
+            .collect())
+    }
+}
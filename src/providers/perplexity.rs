@@ -0,0 +1,137 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::config::AgentConfig;
+use crate::llm::{LLMRequest, LLMResponse, LLM};
+use crate::providers::openai_compat::{ChatResponse, OpenAICompatibleProvider};
+
+/// Perplexity's OpenAI-compatible endpoint.
+pub const PERPLEXITY_API_BASE: &str = "https://api.perplexity.ai";
+
+pub const SONAR: &str = "sonar";
+pub const SONAR_PRO: &str = "sonar-pro";
+
+/// How recent the web results an answer is grounded in must be.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchRecency {
+    Hour,
+    Day,
+    Week,
+    Month,
+}
+
+/// A Sonar answer with the source URLs it cites, numbered from 1 to match
+/// the `[1]`-style markers in its text.
+#[derive(Debug, Clone)]
+pub struct PerplexityResponse {
+    pub response: LLMResponse,
+    pub citations: Vec<String>,
+}
+
+impl PerplexityResponse {
+    /// The citations as a numbered `Sources:` list, or an empty string without any.
+    pub fn sources(&self) -> String {
+        let mut sources = String::new();
+        if !self.citations.is_empty() {
+            sources.push_str("Sources:");
+            for (i, url) in self.citations.iter().enumerate() {
+                sources.push_str(&format!("\n[{}] {}", i + 1, url));
+            }
+        }
+        sources
+    }
+}
+
+/// The Chat Completions body with Perplexity's `citations`.
+#[derive(Deserialize, Debug)]
+struct SonarResponse {
+    #[serde(flatten)]
+    chat: ChatResponse,
+    #[serde(default)]
+    citations: Vec<String>,
+}
+
+/// A provider for Perplexity's web-grounded Sonar models. `invoke` returns
+/// only the answer, so citations never enter the conversation history; use
+/// [`PerplexityProvider::invoke_with_citations`] for them.
+pub struct PerplexityProvider(OpenAICompatibleProvider);
+
+impl PerplexityProvider {
+    /// Creates a provider for `config.model` using the Perplexity key stored at `key_file_path`.
+    pub async fn new(config: AgentConfig, key_file_path: &Path) -> Result<Self> {
+        Ok(Self(OpenAICompatibleProvider::new("Perplexity", config, PERPLEXITY_API_BASE, key_file_path).await?))
+    }
+
+    /// Limits web search to these domains; prefix a domain with `-` to exclude it instead.
+    pub fn with_search_domain_filter(self, domains: Vec<String>) -> Self {
+        Self(self.0.with_body_field("search_domain_filter", domains.into()))
+    }
+
+    /// Sets `return_citations`, which asks for the source URLs behind the answer.
+    pub fn with_return_citations(self, return_citations: bool) -> Self {
+        Self(self.0.with_body_field("return_citations", return_citations.into()))
+    }
+
+    /// Only grounds answers in results published within `recency`.
+    pub fn with_search_recency_filter(self, recency: SearchRecency) -> Self {
+        let value = serde_json::to_value(recency).expect("SearchRecency serializes to a string");
+        Self(self.0.with_body_field("search_recency_filter", value))
+    }
+
+    /// Points the provider at a different server, e.g. a proxy or a mock.
+    pub fn with_base_url(self, base_url: impl Into<String>) -> Self {
+        Self(self.0.with_base_url(base_url))
+    }
+
+    /// Sends `request` and returns the answer with the URLs it cites.
+    pub async fn invoke_with_citations(&self, request: &LLMRequest) -> Result<PerplexityResponse> {
+        let (parsed, latency_ms) = self.0.chat::<SonarResponse>(request).await?;
+        Ok(PerplexityResponse { response: parsed.chat.into_llm_response(latency_ms), citations: parsed.citations })
+    }
+}
+
+#[async_trait]
+impl LLM for PerplexityProvider {
+    async fn invoke(&self, request: &LLMRequest) -> Result<LLMResponse> {
+        self.0.invoke(request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::Message;
+    use crate::providers::test_support::{key_file, mock_server};
+
+    #[tokio::test]
+    async fn sends_search_options_and_keeps_citations_apart() {
+        let key_file = key_file("pplx-test\n");
+        let body = r#"{"choices":[{"message":{"content":"It rained [1][2]."},"finish_reason":"stop"}],"usage":{"prompt_tokens":12,"completion_tokens":5},"citations":["https://a.example/news","https://b.example/weather"]}"#;
+        let (url, server) = mock_server(vec![("200 OK", body), ("200 OK", body)]).await;
+
+        let config = AgentConfig { model: SONAR.to_string(), ..AgentConfig::default() };
+        let provider = PerplexityProvider::new(config, key_file.path())
+            .await
+            .unwrap()
+            .with_base_url(url)
+            .with_search_domain_filter(vec!["a.example".to_string()])
+            .with_return_citations(true)
+            .with_search_recency_filter(SearchRecency::Week);
+        let request = LLMRequest { system_prompt: String::new(), messages: vec![Message::user("Did it rain?")], model: None };
+        let cited = provider.invoke_with_citations(&request).await.unwrap();
+        let plain = provider.invoke(&request).await.unwrap();
+
+        let raw = server.await.unwrap().remove(0);
+        assert!(raw.starts_with("POST /chat/completions "), "{}", raw);
+        assert!(raw.contains(r#""search_domain_filter":["a.example"]"#), "{}", raw);
+        assert!(raw.contains(r#""return_citations":true"#), "{}", raw);
+        assert!(raw.contains(r#""search_recency_filter":"week""#), "{}", raw);
+        assert_eq!(cited.response.content, "It rained [1][2].");
+        assert_eq!(cited.sources(), "Sources:\n[1] https://a.example/news\n[2] https://b.example/weather");
+        assert_eq!((cited.response.input_tokens, cited.response.output_tokens), (12, 5));
+        assert_eq!(plain.content, "It rained [1][2].");
+    }
+}
@@ -0,0 +1,99 @@
+//! A mock HTTP server for the provider tests: it accepts one connection per
+//! canned reply, closes each after replying, and hands back the raw requests.
+
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+/// A temporary key file holding `contents`. It's deleted when dropped, so a
+/// failing test doesn't leave it behind.
+pub(crate) fn key_file(contents: &str) -> tempfile::NamedTempFile {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(file.path(), contents).unwrap();
+    file
+}
+
+/// Serves one request per (status line, JSON body) reply, in order, and
+/// returns the base URL and a handle to the raw request texts.
+pub(crate) async fn mock_server<B: Into<String>>(replies: Vec<(&'static str, B)>) -> (String, JoinHandle<Vec<String>>) {
+    serve(replies, "application/json", true).await
+}
+
+/// Serves a single 200 reply with `body`, returning the raw request text.
+pub(crate) async fn mock_reply(body: &str) -> (String, JoinHandle<String>) {
+    let (url, server) = mock_server(vec![("200 OK", body)]).await;
+    (url, tokio::spawn(async move { server.await.unwrap().remove(0) }))
+}
+
+/// Like [`mock_server`], but each body is a server-sent event stream sent
+/// without a length, so one without a final event looks like a dropped stream.
+pub(crate) async fn mock_event_stream<B: Into<String>>(replies: Vec<(&'static str, B)>) -> (String, JoinHandle<Vec<String>>) {
+    serve(replies, "text/event-stream", false).await
+}
+
+/// Serves one event stream sent in `events`, pausing `gap` before each after
+/// the first, so a reader sees a slow but live stream (or a stalled one).
+pub(crate) async fn mock_slow_event_stream(events: Vec<String>, gap: Duration) -> (String, JoinHandle<Vec<String>>) {
+    serve_chunks(vec![("200 OK", events)], "text/event-stream", false, gap).await
+}
+
+async fn serve<B: Into<String>>(
+    replies: Vec<(&'static str, B)>,
+    content_type: &'static str,
+    with_length: bool,
+) -> (String, JoinHandle<Vec<String>>) {
+    let replies = replies.into_iter().map(|(status, body)| (status, vec![body.into()])).collect();
+    serve_chunks(replies, content_type, with_length, Duration::ZERO).await
+}
+
+async fn serve_chunks(
+    replies: Vec<(&'static str, Vec<String>)>,
+    content_type: &'static str,
+    with_length: bool,
+    gap: Duration,
+) -> (String, JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let handle = tokio::spawn(async move {
+        let mut requests = Vec::new();
+        for (status, chunks) in replies {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut raw = Vec::new();
+            let mut buf = [0; 4096];
+            // Read until the whole body named by Content-Length has arrived.
+            loop {
+                let n = socket.read(&mut buf).await.unwrap();
+                raw.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&raw).to_string();
+                if let Some(header_end) = text.find("\r\n\r\n") {
+                    let length = text[..header_end]
+                        .lines()
+                        .find_map(|line| line.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                        .unwrap_or(0);
+                    if raw.len() >= header_end + 4 + length {
+                        break;
+                    }
+                }
+            }
+            requests.push(String::from_utf8(raw).unwrap());
+            let length = match with_length {
+                true => format!("content-length: {}\r\n", chunks.iter().map(String::len).sum::<usize>()),
+                false => String::new(),
+            };
+            let head = format!("HTTP/1.1 {}\r\ncontent-type: {}\r\n{}connection: close\r\n\r\n", status, content_type, length);
+            socket.write_all(head.as_bytes()).await.unwrap();
+            for (i, chunk) in chunks.iter().enumerate() {
+                if i > 0 {
+                    tokio::time::sleep(gap).await;
+                }
+                // A client that gave up on a stalled stream has closed the connection.
+                if socket.write_all(chunk.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+        }
+        requests
+    });
+    (url, handle)
+}
@@ -0,0 +1,75 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::path::Path;
+
+use crate::config::AgentConfig;
+use crate::llm::{LLMRequest, LLMResponse, ModelInfo, LLM};
+use crate::providers::openai_compat::OpenAICompatibleProvider;
+
+/// Together AI's OpenAI-compatible endpoint.
+pub const TOGETHER_API_BASE: &str = "https://api.together.xyz/v1";
+
+pub const LLAMA_3_1_70B_TURBO: &str = "meta-llama/Meta-Llama-3.1-70B-Instruct-Turbo";
+pub const MIXTRAL_8X7B: &str = "mistralai/Mixtral-8x7B-Instruct-v0.1";
+
+/// A provider for open models hosted by Together AI, such as Llama, Mixtral,
+/// and Qwen. Set `config.model` to a Together model id. Prompt cache hits are
+/// reported in `LLMResponse::cache_read_tokens`.
+pub struct TogetherAIProvider(OpenAICompatibleProvider);
+
+impl TogetherAIProvider {
+    /// Creates a provider using the Together key stored at `key_file_path`.
+    pub async fn new(config: AgentConfig, key_file_path: &Path) -> Result<Self> {
+        Ok(Self(OpenAICompatibleProvider::new("Together AI", config, TOGETHER_API_BASE, key_file_path).await?))
+    }
+
+    /// Points the provider at a different server, e.g. a proxy or a mock.
+    pub fn with_base_url(self, base_url: impl Into<String>) -> Self {
+        Self(self.0.with_base_url(base_url))
+    }
+}
+
+#[async_trait]
+impl LLM for TogetherAIProvider {
+    async fn invoke(&self, request: &LLMRequest) -> Result<LLMResponse> {
+        self.0.invoke(request).await
+    }
+
+    /// Every hosted model, with its display name and context length.
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        self.0.list_models().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::Message;
+    use crate::providers::test_support::{key_file, mock_server};
+
+    #[tokio::test]
+    async fn reports_cache_hits_and_model_context_lengths() {
+        let key_file = key_file("together-test\n");
+        let (url, server) = mock_server(vec![
+            ("200 OK", r#"{"choices":[{"message":{"content":"pong"},"finish_reason":"stop"}],"usage":{"prompt_tokens":40,"completion_tokens":1,"prompt_tokens_details":{"cached_tokens":32}}}"#),
+            ("200 OK", r#"[{"id":"meta-llama/Meta-Llama-3.1-70B-Instruct-Turbo","display_name":"Llama 3.1 70B","context_length":131072,"type":"chat"}]"#),
+        ])
+        .await;
+
+        let config = AgentConfig { model: LLAMA_3_1_70B_TURBO.to_string(), ..AgentConfig::default() };
+        let provider = TogetherAIProvider::new(config, key_file.path()).await.unwrap().with_base_url(url);
+        let response = provider
+            .invoke(&LLMRequest { system_prompt: String::new(), messages: vec![Message::user("ping")], model: None })
+            .await
+            .unwrap();
+        let models = provider.list_models().await.unwrap();
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("POST /chat/completions "), "{}", requests[0]);
+        assert!(requests[1].starts_with("GET /models "), "{}", requests[1]);
+        assert_eq!(response.cache_read_tokens, Some(32));
+        assert_eq!(models[0].id, LLAMA_3_1_70B_TURBO);
+        assert_eq!(models[0].display_name.as_deref(), Some("Llama 3.1 70B"));
+        assert_eq!(models[0].context_length, Some(131_072));
+    }
+}
@@ -0,0 +1,325 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use gcp_auth::TokenProvider;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::config::{AgentConfig, OPTIONAL_SAMPLING};
+use crate::llm::{Content, ImageSource, LLMRequest, LLMResponse, Message, LLM};
+use crate::providers::http;
+
+/// The OAuth scope Vertex AI requests are authorized with.
+const CLOUD_PLATFORM_SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+
+// --- Configuration ---
+
+/// `AgentConfig` plus the fields that route a request to a Gemini model on Vertex AI.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VertexAgentConfig {
+    /// Shared generation settings (max_tokens, temperature, timeouts, proxy, ...).
+    /// `base.model` is ignored in favor of `model`.
+    #[serde(default)]
+    pub base: AgentConfig,
+    pub project_id: String,
+    /// GCP region, e.g. `us-central1`.
+    pub region: String,
+    /// Gemini model, e.g. `gemini-1.5-pro`.
+    pub model: String,
+}
+
+impl VertexAgentConfig {
+    pub fn new(base: AgentConfig, project_id: impl Into<String>, region: impl Into<String>, model: impl Into<String>) -> Self {
+        Self { base, project_id: project_id.into(), region: region.into(), model: model.into() }
+    }
+
+    /// The regional Vertex AI API root.
+    pub fn api_base(&self) -> String {
+        format!("https://{}-aiplatform.googleapis.com", self.region)
+    }
+
+    /// The `generateContent` path for `model`, or the configured model when
+    /// `None`, relative to `api_base`.
+    pub fn generate_content_path(&self, model: Option<&str>) -> String {
+        format!(
+            "/v1/projects/{}/locations/{}/publishers/google/models/{}:generateContent",
+            self.project_id,
+            self.region,
+            model.unwrap_or(&self.model)
+        )
+    }
+}
+
+// --- API Data Structures ---
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContentRequest<'a> {
+    contents: Vec<GeminiContent<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<GeminiContent<'a>>,
+    generation_config: GenerationConfig<'a>,
+}
+
+#[derive(Serialize, Debug)]
+struct GeminiContent<'a> {
+    /// `user` or `model`; omitted for the system instruction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    role: Option<&'static str>,
+    parts: Vec<GeminiPart<'a>>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+enum GeminiPart<'a> {
+    Text(&'a str),
+    InlineData {
+        #[serde(rename = "mimeType")]
+        mime_type: &'a str,
+        data: &'a str,
+    },
+    FileData {
+        #[serde(rename = "mimeType")]
+        mime_type: &'a str,
+        #[serde(rename = "fileUri")]
+        file_uri: &'a str,
+    },
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GenerationConfig<'a> {
+    max_output_tokens: u32,
+    temperature: f32,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    stop_sequences: &'a [String],
+}
+
+impl<'a> From<&'a Message> for GeminiContent<'a> {
+    fn from(message: &'a Message) -> Self {
+        let parts = message
+            .content
+            .iter()
+            .map(|part| match part {
+                Content::Text(text) => GeminiPart::Text(text),
+                Content::Image { source: ImageSource::Base64(data), media_type } => {
+                    GeminiPart::InlineData { mime_type: media_type, data }
+                }
+                Content::Image { source: ImageSource::Url(url), media_type } => {
+                    GeminiPart::FileData { mime_type: media_type, file_uri: url }
+                }
+            })
+            .collect();
+        let role = if message.role == "assistant" { "model" } else { "user" };
+        Self { role: Some(role), parts }
+    }
+}
+
+fn build_request<'a>(config: &'a AgentConfig, request: &'a LLMRequest) -> GenerateContentRequest<'a> {
+    GenerateContentRequest {
+        contents: request.messages.iter().map(GeminiContent::from).collect(),
+        system_instruction: (!request.system_prompt.is_empty()).then(|| GeminiContent {
+            role: None,
+            parts: vec![GeminiPart::Text(&request.system_prompt)],
+        }),
+        generation_config: GenerationConfig {
+            max_output_tokens: config.max_tokens,
+            temperature: config.temperature,
+            stop_sequences: &config.stop_sequences,
+        },
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContentResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+    #[serde(default)]
+    usage_metadata: UsageMetadata,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Candidate {
+    content: Option<CandidateContent>,
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct CandidateContent {
+    #[serde(default)]
+    parts: Vec<CandidatePart>,
+}
+
+#[derive(Deserialize, Debug)]
+struct CandidatePart {
+    text: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct UsageMetadata {
+    #[serde(default)]
+    prompt_token_count: u32,
+    #[serde(default)]
+    candidates_token_count: u32,
+}
+
+impl GenerateContentResponse {
+    fn into_llm_response(self, latency_ms: u64) -> LLMResponse {
+        let candidate = self.candidates.into_iter().next();
+        let finish_reason = candidate.as_ref().and_then(|c| c.finish_reason.clone()).unwrap_or_default();
+        // Translate to the Anthropic stop reasons the rest of the crate expects.
+        let stop_reason = match finish_reason.as_str() {
+            "STOP" => "end_turn".to_string(),
+            "MAX_TOKENS" => "max_tokens".to_string(),
+            other => other.to_ascii_lowercase(),
+        };
+        let content = candidate
+            .and_then(|c| c.content)
+            .map(|content| content.parts.into_iter().filter_map(|part| part.text).collect::<String>())
+            .unwrap_or_default();
+        LLMResponse {
+            content,
+            input_tokens: self.usage_metadata.prompt_token_count,
+            output_tokens: self.usage_metadata.candidates_token_count,
+            stop_reason,
+            // Gemini reports a matched stop sequence as a plain STOP.
+            stop_sequence: None,
+            latency_ms,
+            cache_read_tokens: None,
+            truncated: false,
+        }
+    }
+}
+
+// --- Authentication ---
+
+/// Where access tokens come from.
+enum TokenSource {
+    /// A token obtained elsewhere, used until the caller replaces the provider.
+    Static(String),
+    /// Application Default Credentials, which cache and refresh their own tokens.
+    Adc(Arc<dyn TokenProvider>),
+}
+
+// --- Vertex AI Provider ---
+
+/// A provider for Gemini models on Google Cloud Vertex AI.
+pub struct VertexAIProvider {
+    client: Client,
+    config: VertexAgentConfig,
+    base_url: String,
+    source: TokenSource,
+}
+
+impl VertexAIProvider {
+    /// Authenticates with Application Default Credentials: the service
+    /// account key or user credentials named by `GOOGLE_APPLICATION_CREDENTIALS`
+    /// or written by `gcloud auth application-default login`, else the GCP
+    /// metadata server, else the `gcloud` CLI.
+    pub async fn new(config: VertexAgentConfig) -> Result<Self> {
+        let provider = gcp_auth::provider().await.context("No Google Cloud credentials found")?;
+        Self::with_source(config, TokenSource::Adc(provider))
+    }
+
+    /// Authenticates with an access token obtained elsewhere, e.g. from
+    /// `gcloud auth print-access-token`. It is never refreshed.
+    pub fn with_access_token(config: VertexAgentConfig, token: impl Into<String>) -> Result<Self> {
+        Self::with_source(config, TokenSource::Static(token.into()))
+    }
+
+    fn with_source(config: VertexAgentConfig, source: TokenSource) -> Result<Self> {
+        config.base.warn_unsent_sampling("Vertex AI", OPTIONAL_SAMPLING);
+        Ok(Self {
+            client: http::build_client(&config.base)?,
+            base_url: config.api_base(),
+            config,
+            source,
+        })
+    }
+
+    /// Points the provider at a different server, e.g. a private endpoint or a mock.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Returns an access token, which ADC refreshes when it's close to expiry.
+    async fn access_token(&self) -> Result<String> {
+        match &self.source {
+            TokenSource::Static(token) => Ok(token.clone()),
+            TokenSource::Adc(provider) => {
+                let token = provider.token(&[CLOUD_PLATFORM_SCOPE]).await.context("Failed to fetch a Google access token")?;
+                Ok(token.as_str().to_string())
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl LLM for VertexAIProvider {
+    async fn invoke(&self, request: &LLMRequest) -> Result<LLMResponse> {
+        let body = build_request(&self.config.base, request);
+
+        let mut builder = self
+            .client
+            .post(format!("{}{}", self.base_url, self.config.generate_content_path(request.model.as_deref())))
+            .bearer_auth(self.access_token().await?);
+        for (name, value) in &self.config.base.extra_headers {
+            builder = builder.header(name, value);
+        }
+
+        let started = Instant::now();
+        let response = builder
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| http::transport_error(&self.config.base, e, "Failed to send request to Vertex AI"))?;
+
+        let parsed: GenerateContentResponse = http::check_status(response)
+            .await?
+            .json()
+            .await
+            .map_err(|e| http::transport_error(&self.config.base, e, "Failed to parse Vertex AI response"))?;
+
+        Ok(parsed.into_llm_response(started.elapsed().as_millis() as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::test_support::mock_reply;
+
+    #[tokio::test]
+    async fn sends_gemini_contents_to_the_model_path() {
+        let (url, server) = mock_reply(r#"{"candidates":[{"content":{"role":"model","parts":[{"text":"po"},{"text":"ng"}]},"finishReason":"STOP"}],"usageMetadata":{"promptTokenCount":6,"candidatesTokenCount":1,"totalTokenCount":7}}"#).await;
+        let config = VertexAgentConfig::new(AgentConfig::default(), "my-project", "us-central1", "gemini-1.5-pro");
+        let provider = VertexAIProvider::with_access_token(config, "token").unwrap().with_base_url(url);
+        let response = provider
+            .invoke(&LLMRequest {
+                system_prompt: "Be brief.".to_string(),
+                messages: vec![Message::user("hi"), Message::assistant("hello"), Message::user("ping")],
+                model: None,
+            })
+            .await
+            .unwrap();
+
+        let raw = server.await.unwrap();
+        assert!(
+            raw.starts_with("POST /v1/projects/my-project/locations/us-central1/publishers/google/models/gemini-1.5-pro:generateContent "),
+            "{}",
+            raw
+        );
+        assert!(raw.to_ascii_lowercase().contains("authorization: bearer token\r\n"), "{}", raw);
+        assert!(raw.contains(r#""systemInstruction":{"parts":[{"text":"Be brief."}]}"#), "{}", raw);
+        assert!(raw.contains(r#"{"role":"model","parts":[{"text":"hello"}]}"#), "{}", raw);
+        assert!(raw.contains(r#""maxOutputTokens":4096"#), "{}", raw);
+        assert_eq!(response.content, "pong");
+        assert_eq!((response.input_tokens, response.output_tokens), (6, 1));
+        assert_eq!(response.stop_reason, "end_turn");
+    }
+}
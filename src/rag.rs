@@ -0,0 +1,166 @@
+//! Retrieval-augmented generation: embed the user's question, pull the most
+//! similar chunks out of a `VectorStore`, and hand them to the LLM as context.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::embeddings::EmbeddingProvider;
+use crate::llm::{LLMRequest, LLMResponse, LLM};
+use crate::vector_store::VectorStore;
+
+/// A piece of an ingested document, stored as `VectorStore` metadata.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentChunk {
+    pub source: String,
+    pub text: String,
+}
+
+/// Splits documents into overlapping chunks sized for embedding.
+pub struct DocumentChunker;
+
+impl DocumentChunker {
+    /// Splits `text` into chunks of `chunk_size` tokens, each sharing `overlap`
+    /// tokens with the previous one. Whitespace-separated words stand in for
+    /// tokens, which is close enough for sizing retrieval chunks.
+    pub fn chunk_by_tokens(text: &str, chunk_size: usize, overlap: usize) -> Vec<String> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        if words.is_empty() || chunk_size == 0 {
+            return Vec::new();
+        }
+        let step = chunk_size.saturating_sub(overlap).max(1);
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        loop {
+            let end = (start + chunk_size).min(words.len());
+            chunks.push(words[start..end].join(" "));
+            if end == words.len() {
+                break;
+            }
+            start += step;
+        }
+        chunks
+    }
+}
+
+/// Reads `path`, chunks it, embeds each chunk, and inserts them into `store`.
+/// Returns the number of chunks added, which is 0 for a file with no words.
+pub async fn ingest_file(
+    path: &Path,
+    chunk_size: usize,
+    overlap: usize,
+    embedder: &dyn EmbeddingProvider,
+    store: &mut VectorStore<DocumentChunk>,
+) -> Result<usize> {
+    let text = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let chunks = DocumentChunker::chunk_by_tokens(&text, chunk_size, overlap);
+    if chunks.is_empty() {
+        return Ok(0);
+    }
+    let inputs: Vec<&str> = chunks.iter().map(String::as_str).collect();
+    let embeddings = embedder.embed(&inputs).await?;
+
+    let source = path.display().to_string();
+    for (embedding, text) in embeddings.into_iter().zip(chunks.iter()) {
+        store.insert(
+            embedding,
+            DocumentChunk {
+                source: source.clone(),
+                text: text.clone(),
+            },
+        );
+    }
+    Ok(chunks.len())
+}
+
+/// Wraps an `LLM`, adding retrieved context to the system prompt of every request.
+pub struct RagPipeline {
+    embedder: Box<dyn EmbeddingProvider>,
+    store: VectorStore<DocumentChunk>,
+    llm: Box<dyn LLM>,
+    top_k: usize,
+}
+
+impl RagPipeline {
+    pub fn new(
+        embedder: Box<dyn EmbeddingProvider>,
+        store: VectorStore<DocumentChunk>,
+        llm: Box<dyn LLM>,
+        top_k: usize,
+    ) -> Self {
+        Self {
+            embedder,
+            store,
+            llm,
+            top_k,
+        }
+    }
+
+    /// Builds the `<context>` section appended to the system prompt.
+    fn context_section(chunks: &[(f32, &DocumentChunk)]) -> String {
+        let mut section = String::from(
+            "Use the following retrieved context to answer if it is relevant.\n<context>\n",
+        );
+        for (i, (_, chunk)) in chunks.iter().enumerate() {
+            section.push_str(&format!("[{}] ({})\n{}\n\n", i + 1, chunk.source, chunk.text));
+        }
+        section.push_str("</context>");
+        section
+    }
+}
+
+#[async_trait]
+impl LLM for RagPipeline {
+    async fn invoke(&self, request: &LLMRequest) -> Result<LLMResponse> {
+        let Some(question) = request.messages.iter().rev().find(|m| m.role == "user") else {
+            return self.llm.invoke(request).await;
+        };
+        if self.store.is_empty() {
+            return self.llm.invoke(request).await;
+        }
+
+        let embedding = self.embedder.embed_one(&question.text_content()).await?;
+        let retrieved = self.store.query(&embedding, self.top_k);
+
+        let mut augmented = request.clone();
+        augmented.system_prompt = format!(
+            "{}\n\n{}",
+            request.system_prompt,
+            Self::context_section(&retrieved)
+        );
+        self.llm.invoke(&augmented).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fails every call, for paths that must not embed anything.
+    struct NoEmbeddings;
+
+    #[async_trait]
+    impl EmbeddingProvider for NoEmbeddings {
+        async fn embed(&self, _texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+            anyhow::bail!("embed should not be called")
+        }
+
+        fn dimensions(&self) -> usize {
+            3
+        }
+    }
+
+    #[tokio::test]
+    async fn files_without_words_embed_nothing() {
+        let path = std::env::temp_dir().join(format!("ra1-rag-empty-{}.txt", std::process::id()));
+        std::fs::write(&path, " \n\t\n").unwrap();
+        let mut store = VectorStore::new();
+        let added = ingest_file(&path, 100, 10, &NoEmbeddings, &mut store).await;
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(added.unwrap(), 0);
+        assert_eq!(store.len(), 0);
+    }
+}
@@ -0,0 +1,31 @@
+//! Scripted conversations: a fixed list of user messages replayed turn by turn.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+/// One line of a script file: a bare JSON string or `{"message": "..."}`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScriptLine {
+    Text(String),
+    Message { message: String },
+}
+
+/// Reads the user messages from a JSONL script, skipping blank lines.
+pub fn read_script_file(path: &Path) -> Result<Vec<String>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read script {}", path.display()))?;
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let parsed: ScriptLine = serde_json::from_str(line)
+                .with_context(|| format!("Invalid script line {} of {}", i + 1, path.display()))?;
+            Ok(match parsed {
+                ScriptLine::Text(message) | ScriptLine::Message { message } => message,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,680 @@
+//! Saved conversations, one JSON or JSON Lines file per session in the
+//! sessions directory, and search across them.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::llm::{LLMRequest, LLMResponse, Message, LLM};
+use crate::suggest;
+use crate::template::civil_from_days;
+
+/// System prompt for the secondary call that names a conversation.
+pub const TITLE_SYSTEM_PROMPT: &str = "You name conversations with short, specific titles.";
+
+/// Opens the user message that asks for a title; the first exchange follows it.
+pub const TITLE_INSTRUCTION: &str = "Write a 3-5 word title for the conversation below. \
+Reply with the title only, without quotes or a trailing period.";
+
+/// Longest slug taken from a title for a session id.
+const MAX_SLUG_CHARS: usize = 48;
+
+/// Characters of context kept on each side of a search match.
+const SNIPPET_RADIUS: usize = 50;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    /// The UTC start time as `YYYYMMDD-HHMMSS`, with a `-N` suffix if that was taken.
+    pub id: String,
+    /// Seconds since the Unix epoch.
+    pub created_at: u64,
+    /// Seconds since the Unix epoch.
+    pub updated_at: u64,
+    pub model: String,
+    #[serde(default)]
+    pub system_prompt: String,
+    #[serde(default)]
+    pub messages: Vec<Message>,
+    #[serde(default)]
+    pub input_tokens: u64,
+    #[serde(default)]
+    pub output_tokens: u64,
+    /// Zero when the model had no known pricing.
+    #[serde(default)]
+    pub cost_usd: f64,
+    /// A short name for the conversation, from `--auto-title`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// User-defined labels, e.g. `work` or `debugging`, kept sorted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// The session this one was forked from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_session_id: Option<String>,
+    /// How many of the parent's messages this fork starts with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forked_at_turn: Option<usize>,
+    /// How the session is stored: the format it was loaded from, or the one
+    /// to save a new session in.
+    #[serde(skip)]
+    pub format: SessionFormat,
+    /// Whether loading skipped a malformed last line, left by an interrupted
+    /// write; the next `append` rewrites the file so it doesn't end up mid-file.
+    #[serde(skip)]
+    torn: bool,
+}
+
+/// The layout of a session file, told apart by its extension.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionFormat {
+    /// One pretty-printed JSON document, `<id>.json`.
+    #[default]
+    Json,
+    /// JSON Lines, `<id>.jsonl`: a header line with everything but the
+    /// messages (system prompt, token totals, and so on), then one message
+    /// per line. New messages can be appended, followed by a fresh header,
+    /// without rewriting the file; the last header wins.
+    Jsonl,
+}
+
+impl SessionFormat {
+    pub const ALL: [SessionFormat; 2] = [SessionFormat::Json, SessionFormat::Jsonl];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            SessionFormat::Json => "json",
+            SessionFormat::Jsonl => "jsonl",
+        }
+    }
+
+    /// The format of the file at `path`, from its extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?;
+        Self::ALL.into_iter().find(|format| extension == format.extension())
+    }
+}
+
+impl Session {
+    /// An empty session started now, with an id not yet used in `dir`.
+    pub fn new(dir: &Path, model: &str, system_prompt: &str) -> Self {
+        let now = unix_now();
+        Self {
+            id: unused_id(dir, &session_id(now)),
+            created_at: now,
+            updated_at: now,
+            model: model.to_string(),
+            system_prompt: system_prompt.to_string(),
+            messages: Vec::new(),
+            input_tokens: 0,
+            output_tokens: 0,
+            cost_usd: 0.0,
+            title: None,
+            tags: Vec::new(),
+            parent_session_id: None,
+            forked_at_turn: None,
+            format: SessionFormat::Json,
+            torn: false,
+        }
+    }
+
+    /// Where the session with `id` is stored in `dir` in `format`.
+    pub fn path(dir: &Path, id: &str, format: SessionFormat) -> PathBuf {
+        dir.join(format!("{}.{}", id, format.extension()))
+    }
+
+    /// The file in `dir` holding the session with `id`, in whichever format.
+    pub fn find(dir: &Path, id: &str) -> Option<PathBuf> {
+        SessionFormat::ALL.into_iter().map(|format| Self::path(dir, id, format)).find(|path| path.exists())
+    }
+
+    /// Reads the session with `id` from `dir`, suggesting a close id if there's none.
+    pub fn load_id(dir: &Path, id: &str) -> Result<Self> {
+        let Some(path) = Self::find(dir, id) else {
+            let ids: Vec<String> = list(dir)?.into_iter().map(|session| session.id).collect();
+            match suggest::closest(id, ids.iter().map(String::as_str)) {
+                Some(suggestion) => anyhow::bail!("No session '{}'. Did you mean '{}'?", id, suggestion),
+                None => anyhow::bail!("No session '{}' in {}", id, dir.display()),
+            }
+        };
+        Self::load(&path)
+    }
+
+    /// Reads the session stored at `path`, in the format its extension names.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path).with_context(|| format!("Failed to read session {}", path.display()))?;
+        let format = SessionFormat::from_path(path).unwrap_or_default();
+        let session = match format {
+            SessionFormat::Json => serde_json::from_str(&contents).map_err(anyhow::Error::from),
+            SessionFormat::Jsonl => Self::from_lines(&contents),
+        };
+        let session = session.with_context(|| format!("Invalid session file {}", path.display()))?;
+        Ok(Self { format, ..session })
+    }
+
+    /// Parses JSON Lines: a header, then messages, where any later header
+    /// replaces the earlier one but keeps the messages read so far. A
+    /// malformed last line, as an interrupted append leaves, is skipped with
+    /// a warning; one anywhere else fails the load.
+    fn from_lines(contents: &str) -> Result<Self> {
+        let lines: Vec<(usize, &str)> = contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()).collect();
+        let (_, header) = lines.first().context("Missing header line")?;
+        let mut session: Session = serde_json::from_str(header).context("Invalid header on line 1")?;
+        for (position, &(index, line)) in lines.iter().enumerate().skip(1) {
+            if let Err(e) = session.read_line(line, index + 1) {
+                if position + 1 < lines.len() {
+                    return Err(e);
+                }
+                tracing::warn!(line = index + 1, "Skipping the malformed last line of a session file, probably from an interrupted save: {:#}", e);
+                session.torn = true;
+            }
+        }
+        Ok(session)
+    }
+
+    /// Applies line `number` of a JSON Lines file: a message, or a header.
+    fn read_line(&mut self, line: &str, number: usize) -> Result<()> {
+        let value: serde_json::Value = serde_json::from_str(line).with_context(|| format!("Invalid JSON on line {}", number))?;
+        if value.get("role").is_some() {
+            let message = serde_json::from_value(value).with_context(|| format!("Invalid message on line {}", number))?;
+            self.messages.push(message);
+        } else {
+            let header: Session = serde_json::from_value(value).with_context(|| format!("Invalid header on line {}", number))?;
+            *self = Session { messages: std::mem::take(&mut self.messages), ..header };
+        }
+        Ok(())
+    }
+
+    /// The JSON Lines header: every field but the messages.
+    fn header_line(&self) -> Result<String> {
+        let mut header = serde_json::to_value(self)?;
+        if let Some(fields) = header.as_object_mut() {
+            fields.remove("messages");
+        }
+        Ok(serde_json::to_string(&header)?)
+    }
+
+    /// Whether `name` can be used as a session id: letters, digits, `-`, `_`,
+    /// and `.`, not starting with a dot.
+    pub fn is_valid_id(name: &str) -> bool {
+        !name.is_empty()
+            && !name.starts_with('.')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    }
+
+    /// Writes the session into `dir` in its `format`, replacing any earlier
+    /// save atomically so a crash never leaves a half-written file, and
+    /// removing a copy in the other format. Returns the file's path.
+    pub fn save(&self, dir: &Path) -> Result<PathBuf> {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        let path = Self::path(dir, &self.id, self.format);
+        let temp = dir.join(format!(".{}.{}.tmp", self.id, self.format.extension()));
+        let contents = match self.format {
+            SessionFormat::Json => serde_json::to_string_pretty(self)?,
+            SessionFormat::Jsonl => {
+                let mut lines = vec![self.header_line()?];
+                for message in &self.messages {
+                    lines.push(serde_json::to_string(message)?);
+                }
+                lines.join("\n") + "\n"
+            }
+        };
+        std::fs::write(&temp, contents).with_context(|| format!("Failed to write {}", temp.display()))?;
+        std::fs::rename(&temp, &path).with_context(|| format!("Failed to save session {}", path.display()))?;
+        for format in SessionFormat::ALL.into_iter().filter(|&format| format != self.format) {
+            let stale = Self::path(dir, &self.id, format);
+            if stale.exists() {
+                std::fs::remove_file(&stale).with_context(|| format!("Failed to remove {}", stale.display()))?;
+            }
+        }
+        Ok(path)
+    }
+
+    /// Adds `messages[from..]` and a fresh header to the session's JSON Lines
+    /// file in `dir`, which must already hold the first `from` messages,
+    /// instead of rewriting it. Returns the file's path.
+    pub fn append(&mut self, dir: &Path, from: usize) -> Result<PathBuf> {
+        anyhow::ensure!(self.format == SessionFormat::Jsonl, "Only JSON Lines sessions can be appended to");
+        if self.torn {
+            self.torn = false;
+            return self.save(dir);
+        }
+        let path = Self::path(dir, &self.id, self.format);
+        let mut lines = String::new();
+        for message in self.messages.iter().skip(from) {
+            lines.push_str(&serde_json::to_string(message)?);
+            lines.push('\n');
+        }
+        lines.push_str(&self.header_line()?);
+        lines.push('\n');
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open session {}", path.display()))?;
+        file.write_all(lines.as_bytes()).with_context(|| format!("Failed to append to session {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// Adds `tag` unless it's already there; returns whether it was added.
+    pub fn add_tag(&mut self, tag: &str) -> bool {
+        match self.tags.binary_search_by(|existing| existing.as_str().cmp(tag)) {
+            Ok(_) => false,
+            Err(index) => {
+                self.tags.insert(index, tag.to_string());
+                true
+            }
+        }
+    }
+
+    /// Removes `tag`; returns whether it was there.
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        let before = self.tags.len();
+        self.tags.retain(|existing| existing != tag);
+        self.tags.len() != before
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|existing| existing == tag)
+    }
+
+    /// Marks the session as changed now.
+    pub fn touch(&mut self) {
+        self.updated_at = unix_now();
+    }
+}
+
+/// `base`, or `base-N` for the first N from 2 that isn't taken in `dir`.
+pub fn unused_id(dir: &Path, base: &str) -> String {
+    std::iter::once(base.to_string())
+        .chain((2..).map(|n| format!("{}-{}", base, n)))
+        .find(|id| Session::find(dir, id).is_none())
+        .expect("an unused id exists")
+}
+
+/// Asks `llm` for a 3-5 word title for `messages`, of which only the first
+/// exchange is sent. Returns the cleaned-up title, or `None` if the reply
+/// had none, and the call's response, for billing. A title may have no
+/// ASCII letters, and so an empty [`slug`].
+pub async fn generate_title(messages: &[Message], llm: &dyn LLM) -> Result<(Option<String>, LLMResponse)> {
+    let exchange = messages
+        .iter()
+        .take(2)
+        .map(|message| {
+            let speaker = if message.role == "assistant" { "Assistant" } else { "User" };
+            format!("{}: {}", speaker, message.text_content())
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let request = LLMRequest {
+        system_prompt: TITLE_SYSTEM_PROMPT.to_string(),
+        messages: vec![Message::user(&format!("{}\n\n{}", TITLE_INSTRUCTION, exchange))],
+        model: None,
+    };
+    let response = llm.invoke(&request).await.context("Failed to generate a title")?;
+    let title = clean_title(&response.content);
+    Ok(((!title.is_empty()).then_some(title), response))
+}
+
+/// The first line of a model-written title, without surrounding quotes, a
+/// `Title:` label, or trailing punctuation, and at most five words.
+fn clean_title(raw: &str) -> String {
+    let line = raw.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or_default();
+    let line = line.strip_prefix("Title:").unwrap_or(line);
+    let line = line.trim().trim_matches(|c: char| matches!(c, '"' | '\'' | '*' | '`')).trim_end_matches(['.', '!']);
+    line.split_whitespace().take(5).collect::<Vec<_>>().join(" ")
+}
+
+/// `title` as a session id: lowercase letters and digits joined by hyphens.
+pub fn slug(title: &str) -> String {
+    let words: Vec<String> = title
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect();
+    let mut slug = String::new();
+    for word in words {
+        if slug.len() + word.len() + 1 > MAX_SLUG_CHARS {
+            break;
+        }
+        if !slug.is_empty() {
+            slug.push('-');
+        }
+        slug.push_str(&word);
+    }
+    slug
+}
+
+/// A new session starting with the first `at_turn` messages of `session`
+/// (all of them if it has fewer), recording where it branched off. Its id
+/// is freshly timestamped; pass it through [`unused_id`] before saving.
+pub fn fork_session(session: &Session, at_turn: usize) -> Session {
+    let now = unix_now();
+    let at_turn = at_turn.min(session.messages.len());
+    Session {
+        id: session_id(now),
+        created_at: now,
+        updated_at: now,
+        model: session.model.clone(),
+        system_prompt: session.system_prompt.clone(),
+        messages: session.messages[..at_turn].to_vec(),
+        input_tokens: 0,
+        output_tokens: 0,
+        cost_usd: 0.0,
+        title: None,
+        tags: session.tags.clone(),
+        parent_session_id: Some(session.id.clone()),
+        forked_at_turn: Some(at_turn),
+        format: session.format,
+        torn: false,
+    }
+}
+
+/// `sessions` arranged as a tree: each root (newest first) followed by its
+/// forks, depth-first, with their depth. Forks whose parent is missing are roots.
+pub fn tree(sessions: &[Session]) -> Vec<(usize, &Session)> {
+    let ids: std::collections::HashSet<&str> = sessions.iter().map(|session| session.id.as_str()).collect();
+    let is_root = |session: &Session| !session.parent_session_id.as_deref().is_some_and(|parent| ids.contains(parent));
+    let mut ordered = Vec::with_capacity(sessions.len());
+    let mut stack: Vec<(usize, &Session)> = sessions.iter().filter(|session| is_root(session)).rev().map(|session| (0, session)).collect();
+    while let Some((depth, session)) = stack.pop() {
+        ordered.push((depth, session));
+        let children = sessions.iter().filter(|child| child.parent_session_id.as_deref() == Some(session.id.as_str()));
+        stack.extend(children.rev().map(|child| (depth + 1, child)));
+    }
+    ordered
+}
+
+/// Every session in `dir`, most recently updated first. A missing directory
+/// has none; unreadable files are skipped with a warning.
+pub fn list(dir: &Path) -> Result<Vec<Session>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read sessions directory {}", dir.display())),
+    };
+    let mut sessions = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let is_session = SessionFormat::from_path(&path).is_some()
+            && !path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        if !is_session {
+            continue;
+        }
+        match Session::load(&path) {
+            Ok(session) => sessions.push(session),
+            Err(e) => tracing::warn!("Skipping {}: {:#}", path.display(), e),
+        }
+    }
+    sessions.sort_by(|a, b| b.updated_at.cmp(&a.updated_at).then_with(|| b.id.cmp(&a.id)));
+    Ok(sessions)
+}
+
+// --- Search ---
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchQuery {
+    /// Case-insensitive literal text.
+    Keyword(String),
+    /// A pattern in the syntax of the `regex` crate.
+    Regex(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SearchMatch {
+    pub session_id: String,
+    /// Index of the matching message in the session.
+    pub turn_index: usize,
+    pub role: String,
+    /// The match with up to 50 characters on each side, on one line.
+    pub snippet: String,
+}
+
+impl SearchQuery {
+    fn compile(&self) -> Result<Regex> {
+        match self {
+            Self::Keyword(keyword) => {
+                Regex::new(&format!("(?i){}", regex::escape(keyword))).context("Failed to compile keyword")
+            }
+            Self::Regex(pattern) => Regex::new(pattern).with_context(|| format!("Invalid regex '{}'", pattern)),
+        }
+    }
+}
+
+/// The first match in each message of every session in `sessions_dir`,
+/// newest session first and in conversation order within a session.
+pub fn search_sessions(sessions_dir: &Path, query: &SearchQuery) -> Result<Vec<SearchMatch>> {
+    let regex = query.compile()?;
+    let mut matches = Vec::new();
+    for session in list(sessions_dir)? {
+        for (turn_index, message) in session.messages.iter().enumerate() {
+            let text = message.text_content();
+            if let Some(range) = regex.find(&text).map(|found| found.range()) {
+                matches.push(SearchMatch {
+                    session_id: session.id.clone(),
+                    turn_index,
+                    role: message.role.clone(),
+                    snippet: snippet(&text, range.start, range.end),
+                });
+            }
+        }
+    }
+    Ok(matches)
+}
+
+/// `text[start..end]` with up to `SNIPPET_RADIUS` characters either side,
+/// whitespace runs collapsed, and `...` where it was cut.
+fn snippet(text: &str, start: usize, end: usize) -> String {
+    let before: Vec<char> = text[..start].chars().collect();
+    let after: Vec<char> = text[end..].chars().collect();
+    let lead = &before[before.len().saturating_sub(SNIPPET_RADIUS)..];
+    let trail = &after[..after.len().min(SNIPPET_RADIUS)];
+    let raw = format!("{}{}{}", lead.iter().collect::<String>(), &text[start..end], trail.iter().collect::<String>());
+    let mut snippet = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+    if lead.len() < before.len() {
+        snippet.insert_str(0, "...");
+    }
+    if trail.len() < after.len() {
+        snippet.push_str("...");
+    }
+    snippet
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// An id for a session starting now, before checking whether it's taken.
+pub fn current_id() -> String {
+    session_id(unix_now())
+}
+
+/// `secs` since the epoch as a `YYYYMMDD-HHMMSS` UTC timestamp.
+fn session_id(secs: u64) -> String {
+    let secs = secs as i64;
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    let time = secs.rem_euclid(86_400);
+    format!("{:04}{:02}{:02}-{:02}{:02}{:02}", year, month, day, time / 3600, time / 60 % 60, time % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+
+    /// Replies to every request with the same text.
+    struct Canned(&'static str);
+
+    #[async_trait]
+    impl LLM for Canned {
+        async fn invoke(&self, _request: &LLMRequest) -> Result<LLMResponse> {
+            Ok(LLMResponse {
+                content: self.0.to_string(),
+                input_tokens: 30,
+                output_tokens: 5,
+                stop_reason: "end_turn".to_string(),
+                stop_sequence: None,
+                latency_ms: 0,
+                cache_read_tokens: None,
+                truncated: false,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn titles_without_ascii_are_kept() {
+        let messages = [Message::user("こんにちは"), Message::assistant("こんにちは!")];
+        let (title, response) = generate_title(&messages, &Canned("\"日本語の挨拶\"")).await.unwrap();
+        assert_eq!(title.as_deref(), Some("日本語の挨拶"));
+        assert_eq!(slug(title.as_deref().unwrap()), "");
+        assert_eq!(response.output_tokens, 5);
+
+        let (title, response) = generate_title(&messages, &Canned("\n\"\"\n")).await.unwrap();
+        assert_eq!(title, None);
+        assert_eq!(response.input_tokens, 30);
+    }
+
+    #[test]
+    fn searches_saved_sessions_newest_first() {
+        let dir = std::env::temp_dir().join(format!("ra1-session-search-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut older = Session::new(&dir, "m", "");
+        older.messages = vec![Message::user("How do I fix E0502 with TOML in Rust?"), Message::assistant("Use the toml crate.")];
+        older.updated_at = 100;
+        older.save(&dir).unwrap();
+        let mut newer = Session::new(&dir, "m", "");
+        assert_ne!(newer.id, older.id);
+        newer.messages = vec![Message::user(&format!("{}rust{}", "x".repeat(60), "y".repeat(60)))];
+        newer.updated_at = 200;
+        newer.save(&dir).unwrap();
+
+        let found = search_sessions(&dir, &SearchQuery::Keyword("RUST".to_string())).unwrap();
+        assert_eq!(found.iter().map(|m| (&m.session_id, m.turn_index)).collect::<Vec<_>>(), vec![(&newer.id, 0), (&older.id, 0)]);
+        assert_eq!(found[0].snippet, format!("...{}rust{}...", "x".repeat(50), "y".repeat(50)));
+        assert_eq!(found[1].snippet, "How do I fix E0502 with TOML in Rust?");
+
+        let found = search_sessions(&dir, &SearchQuery::Regex(r"E\d{4}|toml crate".to_string())).unwrap();
+        assert_eq!(found.iter().map(|m| m.role.as_str()).collect::<Vec<_>>(), vec!["user", "assistant"]);
+
+        let err = search_sessions(&dir, &SearchQuery::Regex("(unclosed".to_string())).unwrap_err();
+        assert!(format!("{:#}", err).starts_with("Invalid regex '(unclosed'"), "{:#}", err);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn jsonl_sessions_append_and_convert() {
+        let dir = std::env::temp_dir().join(format!("ra1-session-jsonl-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut session = Session::new(&dir, "m", "Be brief.");
+        session.format = SessionFormat::Jsonl;
+        session.messages = vec![Message::user("a"), Message::assistant("b")];
+        session.input_tokens = 10;
+        let path = session.save(&dir).unwrap();
+        assert_eq!(path, dir.join(format!("{}.jsonl", session.id)));
+        session.messages.extend([Message::user("c"), Message::assistant("d")]);
+        session.input_tokens = 25;
+        session.add_tag("work");
+        session.append(&dir, 2).unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().count(), 6);
+        assert!(!contents.lines().next().unwrap().contains("messages"));
+        let loaded = Session::load_id(&dir, &session.id).unwrap();
+        assert_eq!(loaded.format, SessionFormat::Jsonl);
+        assert_eq!(loaded.messages, session.messages);
+        assert_eq!((loaded.system_prompt.as_str(), loaded.input_tokens), ("Be brief.", 25));
+        assert_eq!(loaded.tags, vec!["work"]);
+
+        let mut converted = loaded;
+        converted.format = SessionFormat::Json;
+        converted.save(&dir).unwrap();
+        assert!(!path.exists());
+        assert_eq!(list(&dir).unwrap().len(), 1);
+        assert_eq!(Session::load_id(&dir, &session.id).unwrap().format, SessionFormat::Json);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn a_torn_last_line_is_skipped_and_rewritten() {
+        let dir = std::env::temp_dir().join(format!("ra1-session-torn-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut session = Session::new(&dir, "m", "");
+        session.format = SessionFormat::Jsonl;
+        session.messages = vec![Message::user("a"), Message::assistant("b")];
+        let path = session.save(&dir).unwrap();
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"role\":\"user\",\"content\":[{\"type\":\"te").unwrap();
+
+        let mut loaded = Session::load_id(&dir, &session.id).unwrap();
+        assert_eq!(loaded.messages, session.messages);
+        loaded.messages.extend([Message::user("c"), Message::assistant("d")]);
+        loaded.append(&dir, 2).unwrap();
+        assert_eq!(Session::load_id(&dir, &session.id).unwrap().messages, loaded.messages);
+
+        std::fs::write(&path, "{\"id\":\"x\",\"created_at\":0,\"updated_at\":0,\"model\":\"m\"}\nnot json\n{\"role\":\"user\",\"content\":[]}\n").unwrap();
+        let err = Session::load_id(&dir, &session.id).unwrap_err();
+        assert!(format!("{:#}", err).contains("line 2"), "{:#}", err);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn tags_are_kept_sorted_and_unique() {
+        let mut session = Session::new(Path::new("/nonexistent"), "m", "");
+        assert!(session.add_tag("work"));
+        assert!(session.add_tag("debugging"));
+        assert!(!session.add_tag("work"));
+        assert_eq!(session.tags, vec!["debugging", "work"]);
+        assert!(session.remove_tag("work"));
+        assert!(!session.remove_tag("work"));
+        assert!(session.has_tag("debugging") && !session.has_tag("work"));
+    }
+
+    #[test]
+    fn forks_keep_a_prefix_and_list_under_their_parent() {
+        let mut parent = Session::new(Path::new("/nonexistent"), "m", "Be brief.");
+        parent.messages = vec![Message::user("a"), Message::assistant("b"), Message::user("c"), Message::assistant("d")];
+        let mut fork = fork_session(&parent, 2);
+        fork.id = format!("{}-fork", parent.id);
+        assert_eq!(fork.messages, parent.messages[..2]);
+        assert_eq!(fork.parent_session_id.as_deref(), Some(parent.id.as_str()));
+        assert_eq!((fork.forked_at_turn, fork.system_prompt.as_str()), (Some(2), "Be brief."));
+        assert_eq!(fork_session(&parent, 10).messages.len(), 4);
+
+        let mut other = Session::new(Path::new("/nonexistent"), "m", "");
+        other.id = "other".to_string();
+        let sessions = vec![fork.clone(), other.clone(), parent.clone()];
+        let ordered: Vec<(usize, &str)> = tree(&sessions).into_iter().map(|(depth, s)| (depth, s.id.as_str())).collect();
+        assert_eq!(ordered, vec![(0, "other"), (0, parent.id.as_str()), (1, fork.id.as_str())]);
+    }
+
+    #[test]
+    fn session_ids_are_utc_timestamps() {
+        assert_eq!(session_id(0), "19700101-000000");
+        assert_eq!(session_id(951_827_696), "20000229-123456");
+    }
+
+    #[test]
+    fn names_stay_inside_the_sessions_directory() {
+        assert!(Session::is_valid_id("notes_v2.1"));
+        assert!(!Session::is_valid_id(".hidden"));
+        assert!(!Session::is_valid_id("../escape"));
+        assert!(!Session::is_valid_id("two words"));
+        assert!(!Session::is_valid_id(""));
+    }
+
+    #[test]
+    fn titles_become_tidy_ids() {
+        assert_eq!(clean_title("\n\"Debugging Rust Borrow Errors.\"\nHope this helps"), "Debugging Rust Borrow Errors");
+        assert_eq!(clean_title("Title: a very long title that keeps going on"), "a very long title that");
+        assert_eq!(slug("Fixing E0502 in Rust's TOML parser!"), "fixing-e0502-in-rust-s-toml-parser");
+        assert_eq!(slug("¿Qué?"), "qu");
+        assert!(slug(&"word ".repeat(20)).len() <= MAX_SLUG_CHARS);
+    }
+}
@@ -0,0 +1,143 @@
+//! Slash commands typed at the interactive prompt.
+
+use crate::suggest;
+
+/// A line from the interactive prompt that controls the session instead of
+/// being sent to the model. Arguments are trimmed and may be empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// `exit`, `quit`, `/exit`, or `/quit`.
+    Exit,
+    /// `/help`
+    Help,
+    /// `/persona [name]`
+    Persona(String),
+    /// `/system <prompt>|show|reset`
+    System(String),
+    /// `/model [name]`
+    Model(String),
+    /// `/export <file>`
+    Export(String),
+    /// `/edit [last]`
+    Edit(String),
+    /// `/clear [history|all]`
+    Clear(String),
+    /// `/retry [--temperature T]`
+    Retry(String),
+    /// `/undo [N]`
+    Undo(String),
+    /// `/save [name]`
+    Save(String),
+    /// `/load <name>`
+    Load(String),
+    /// `/tokens`
+    Tokens,
+    /// `/tools`
+    Tools,
+    /// `/tool <name>`
+    Tool(String),
+    /// A `/name` that isn't a command.
+    Unknown(String),
+}
+
+/// Builds a command from its trimmed argument.
+pub type Constructor = fn(String) -> Command;
+
+/// Usage, a one-line description, and the constructor of every command, in
+/// the order `/help` lists them. `parse` and `/help` both read this table.
+pub const COMMANDS: &[(&str, &str, Constructor)] = &[
+    ("/help", "List these commands", |_| Command::Help),
+    ("/edit [last]", "Write a message in $EDITOR; 'last' starts from the previous one", Command::Edit),
+    ("/retry [--temperature T]", "Ask again for a reply to the last message, replacing the one given", Command::Retry),
+    ("/undo [N]", "Remove the last N exchanges (default 1) from the conversation", Command::Undo),
+    (
+        "/clear [history|all]",
+        "Forget the conversation so far; 'all' also resets the session totals (not --budget or --max-turns)",
+        Command::Clear,
+    ),
+    ("/save [name]", "Save the conversation to the sessions directory", Command::Save),
+    ("/load <name>", "Continue a saved session in place of this one", Command::Load),
+    ("/export <file>", "Save a Markdown transcript of the conversation", Command::Export),
+    ("/tokens", "Show how much of the model's context window the conversation uses", |_| Command::Tokens),
+    ("/tools", "List the tools registered in this session", |_| Command::Tools),
+    ("/tool <name>", "Show a tool's description and input schema", Command::Tool),
+    ("/model [name]", "Show or switch the model", Command::Model),
+    ("/system <prompt>|show|reset", "Replace, print, or restore the system prompt", Command::System),
+    ("/persona [name]", "List personas, or switch the system prompt to one", Command::Persona),
+    ("/exit", "End the session (also /quit, exit, quit)", |_| Command::Exit),
+];
+
+/// The `/name` a `COMMANDS` usage starts with.
+fn command_name(usage: &str) -> &str {
+    usage.split_whitespace().next().unwrap_or(usage)
+}
+
+/// Parses a prompt line, or returns `None` for a message to send. Start a
+/// message with `//` to send it with one leading `/` (see [`unescape`]).
+pub fn parse(line: &str) -> Option<Command> {
+    let line = line.trim();
+    if line.eq_ignore_ascii_case("exit") || line.eq_ignore_ascii_case("quit") {
+        return Some(Command::Exit);
+    }
+    let rest = line.strip_prefix('/').filter(|rest| !rest.starts_with('/'))?;
+    let (name, arg) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    if name == "quit" {
+        return Some(Command::Exit);
+    }
+    Some(match COMMANDS.iter().find(|(usage, ..)| command_name(usage)[1..] == *name) {
+        Some((_, _, build)) => build(arg.trim().to_string()),
+        None => Command::Unknown(name.to_string()),
+    })
+}
+
+/// The message text of a line `parse` didn't treat as a command.
+pub fn unescape(line: &str) -> &str {
+    line.strip_prefix("//").map_or(line, |_| &line[1..])
+}
+
+/// The `/help` text: every command in [`COMMANDS`] with its usage and description.
+pub fn help() -> String {
+    let width = COMMANDS.iter().map(|(usage, ..)| usage.len()).max().unwrap_or_default();
+    let lines: Vec<String> = COMMANDS.iter().map(|(usage, about, _)| format!("  {:<width$}  {}", usage, about)).collect();
+    format!("Commands:\n{}\nStart a message with // to send a leading /.", lines.join("\n"))
+}
+
+/// What to tell someone who typed `/name`, which isn't a command.
+pub fn unknown_command_hint(name: &str) -> String {
+    let names = COMMANDS.iter().map(|(usage, ..)| command_name(usage));
+    match suggest::closest(&format!("/{}", name), names) {
+        Some(suggestion) => format!("Unknown command '/{}'. Did you mean {}? Type /help for the list.", name, suggestion),
+        None => format!("Unknown command '/{}'. Type /help for the list, or start with // to send it as a message.", name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_commands_and_leaves_messages_alone() {
+        assert_eq!(parse("  /model claude-3-haiku-20240307 "), Some(Command::Model("claude-3-haiku-20240307".to_string())));
+        assert_eq!(parse("/edit"), Some(Command::Edit(String::new())));
+        assert_eq!(parse("/system  Be terse. "), Some(Command::System("Be terse.".to_string())));
+        assert_eq!(parse("/retry --temperature 1.0"), Some(Command::Retry("--temperature 1.0".to_string())));
+        assert_eq!(parse("QUIT"), Some(Command::Exit));
+        assert_eq!(parse("/modle x"), Some(Command::Unknown("modle".to_string())));
+        assert_eq!(parse("What does /usr hold?"), None);
+        assert_eq!(parse("//usr/bin is where?"), None);
+        assert_eq!(unescape("//usr/bin is where?"), "/usr/bin is where?");
+        assert!(unknown_command_hint("modle").contains("Did you mean /model?"));
+    }
+
+    #[test]
+    fn help_lists_exactly_the_commands_parse_knows() {
+        for (usage, _, build) in COMMANDS {
+            let name = command_name(usage);
+            assert_eq!(parse(&format!("{} arg", name)), Some(build("arg".to_string())), "{}", name);
+            assert!(help().contains(usage));
+        }
+        assert_eq!(parse("/quit"), Some(Command::Exit));
+        assert!(unknown_command_hint("tokns").contains("Did you mean /tokens?"));
+        assert!(unknown_command_hint("frobnicate").contains("Type /help"));
+    }
+}
@@ -0,0 +1,250 @@
+//! Per-session usage analytics, collected turn by turn.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+use crate::llm::LLMResponse;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionStats {
+    pub turn_count: u32,
+    pub total_input_tokens: u32,
+    pub total_output_tokens: u32,
+    /// Zero when the model has no known pricing.
+    pub total_cost_usd: f64,
+    pub avg_turn_latency_ms: f64,
+    pub max_turn_latency_ms: u64,
+    pub p50_latency_ms: u64,
+    pub p99_latency_ms: u64,
+    pub session_duration_secs: u64,
+    pub model: String,
+    #[serde(skip)]
+    latency_quantiles: LatencyQuantiles,
+}
+
+/// Running estimates behind `p50_latency_ms` and `p99_latency_ms`.
+#[derive(Debug, Clone)]
+struct LatencyQuantiles {
+    p50: QuantileEstimator,
+    p99: QuantileEstimator,
+}
+
+impl Default for LatencyQuantiles {
+    fn default() -> Self {
+        Self { p50: QuantileEstimator::new(0.5), p99: QuantileEstimator::new(0.99) }
+    }
+}
+
+impl SessionStats {
+    pub fn new(model: &str) -> Self {
+        Self {
+            model: model.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Adds one successful turn. `cost_usd` is `None` when pricing is unknown.
+    pub fn record_turn(&mut self, response: &LLMResponse, cost_usd: Option<f64>) {
+        let latency_ms = response.latency_ms;
+        self.turn_count += 1;
+        self.total_input_tokens += response.input_tokens;
+        self.total_output_tokens += response.output_tokens;
+        self.total_cost_usd += cost_usd.unwrap_or_default();
+        self.avg_turn_latency_ms +=
+            (latency_ms as f64 - self.avg_turn_latency_ms) / f64::from(self.turn_count);
+        self.max_turn_latency_ms = self.max_turn_latency_ms.max(latency_ms);
+
+        let quantiles = &mut self.latency_quantiles;
+        quantiles.p50.observe(latency_ms as f64);
+        quantiles.p99.observe(latency_ms as f64);
+        self.p50_latency_ms = quantiles.p50.estimate().round() as u64;
+        self.p99_latency_ms = quantiles.p99.estimate().round() as u64;
+    }
+
+    /// Adds the tokens and cost of a call made on the session's behalf, such as
+    /// summarizing old messages, without counting it as a turn.
+    pub fn record_overhead(&mut self, response: &LLMResponse, cost_usd: Option<f64>) {
+        self.total_input_tokens += response.input_tokens;
+        self.total_output_tokens += response.output_tokens;
+        self.total_cost_usd += cost_usd.unwrap_or_default();
+    }
+
+    /// Writes the stats to `path` as pretty-printed JSON.
+    pub fn write_json(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json + "\n")
+            .with_context(|| format!("Failed to write session stats to {}", path.display()))
+    }
+}
+
+/// Nearest-rank `p`th percentile of `sorted`, or 0 when it's empty.
+pub fn percentile(sorted: &[u64], p: usize) -> u64 {
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    sorted.get(rank - 1).copied().unwrap_or_default()
+}
+
+// --- Running Quantiles ---
+
+/// A running estimate of one quantile in constant memory, by the P² algorithm
+/// (Jain and Chlamtac, 1985): five markers track the minimum, the maximum,
+/// the quantile, and points halfway to it, and move as observations arrive.
+/// Exact (nearest-rank) until the sixth observation.
+#[derive(Debug, Clone)]
+pub struct QuantileEstimator {
+    quantile: f64,
+    count: usize,
+    /// Marker heights; the first `count` observations, unsorted, until there are five.
+    heights: [f64; 5],
+    positions: [f64; 5],
+    desired: [f64; 5],
+    increments: [f64; 5],
+}
+
+impl QuantileEstimator {
+    /// Estimates the `quantile`th quantile, from 0.0 to 1.0.
+    pub fn new(quantile: f64) -> Self {
+        Self {
+            quantile,
+            count: 0,
+            heights: [0.0; 5],
+            positions: [1.0, 2.0, 3.0, 4.0, 5.0],
+            desired: [1.0, 1.0 + 2.0 * quantile, 1.0 + 4.0 * quantile, 3.0 + 2.0 * quantile, 5.0],
+            increments: [0.0, quantile / 2.0, quantile, (1.0 + quantile) / 2.0, 1.0],
+        }
+    }
+
+    pub fn observe(&mut self, value: f64) {
+        if self.count < 5 {
+            self.heights[self.count] = value;
+            self.count += 1;
+            if self.count == 5 {
+                self.heights.sort_by(f64::total_cmp);
+            }
+            return;
+        }
+        self.count += 1;
+
+        // The cell the value falls in, widening the extremes to take it.
+        let cell = if value < self.heights[0] {
+            self.heights[0] = value;
+            0
+        } else if value >= self.heights[4] {
+            self.heights[4] = value;
+            3
+        } else {
+            (0..4).find(|&i| value < self.heights[i + 1]).unwrap_or(3)
+        };
+        for position in &mut self.positions[cell + 1..] {
+            *position += 1.0;
+        }
+        for (desired, increment) in self.desired.iter_mut().zip(self.increments) {
+            *desired += increment;
+        }
+
+        // Move each middle marker at most one position toward where it should be.
+        for i in 1..4 {
+            let offset = self.desired[i] - self.positions[i];
+            let room_above = self.positions[i + 1] - self.positions[i] > 1.0;
+            let room_below = self.positions[i - 1] - self.positions[i] < -1.0;
+            if (offset >= 1.0 && room_above) || (offset <= -1.0 && room_below) {
+                let step = offset.signum();
+                let parabolic = self.parabolic(i, step);
+                self.heights[i] = if self.heights[i - 1] < parabolic && parabolic < self.heights[i + 1] {
+                    parabolic
+                } else {
+                    self.linear(i, step)
+                };
+                self.positions[i] += step;
+            }
+        }
+    }
+
+    /// The current estimate, or 0 before any observation.
+    pub fn estimate(&self) -> f64 {
+        if self.count >= 5 {
+            return self.heights[2];
+        }
+        let mut seen = self.heights[..self.count].to_vec();
+        seen.sort_by(f64::total_cmp);
+        let rank = (self.quantile * self.count as f64).ceil().max(1.0) as usize;
+        seen.get(rank - 1).copied().unwrap_or_default()
+    }
+
+    /// Marker `i`'s height moved `step` positions along the parabola through its neighbours.
+    fn parabolic(&self, i: usize, step: f64) -> f64 {
+        let (q, n) = (&self.heights, &self.positions);
+        q[i] + step / (n[i + 1] - n[i - 1])
+            * ((n[i] - n[i - 1] + step) * (q[i + 1] - q[i]) / (n[i + 1] - n[i])
+                + (n[i + 1] - n[i] - step) * (q[i] - q[i - 1]) / (n[i] - n[i - 1]))
+    }
+
+    /// Marker `i`'s height moved `step` positions toward the neighbour on that side.
+    fn linear(&self, i: usize, step: f64) -> f64 {
+        let neighbour = if step > 0.0 { i + 1 } else { i - 1 };
+        let (q, n) = (&self.heights, &self.positions);
+        q[i] + step * (q[neighbour] - q[i]) / (n[neighbour] - n[i])
+    }
+}
+
+impl fmt::Display for SessionStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Model:               {}", self.model)?;
+        writeln!(f, "Turns:               {}", self.turn_count)?;
+        writeln!(f, "Total Input Tokens:  {}", self.total_input_tokens)?;
+        writeln!(f, "Total Output Tokens: {}", self.total_output_tokens)?;
+        writeln!(f, "Total Cost:          ${:.4}", self.total_cost_usd)?;
+        writeln!(f, "Avg Turn Latency:    {:.0} ms", self.avg_turn_latency_ms)?;
+        writeln!(f, "Max Turn Latency:    {} ms", self.max_turn_latency_ms)?;
+        writeln!(f, "p50 / p99 Latency:   {} ms / {} ms", self.p50_latency_ms, self.p99_latency_ms)?;
+        write!(f, "Session Duration:    {}s", self.session_duration_secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(latency_ms: u64) -> LLMResponse {
+        LLMResponse {
+            content: String::new(),
+            input_tokens: 1,
+            output_tokens: 1,
+            stop_reason: "end_turn".to_string(),
+            stop_sequence: None,
+            latency_ms,
+            cache_read_tokens: None,
+            truncated: false,
+        }
+    }
+
+    #[test]
+    fn short_sessions_have_exact_percentiles() {
+        let mut stats = SessionStats::new("model");
+        assert_eq!((stats.p50_latency_ms, stats.p99_latency_ms), (0, 0));
+        for latency_ms in [300, 100, 200] {
+            stats.record_turn(&turn(latency_ms), None);
+        }
+        assert_eq!((stats.p50_latency_ms, stats.p99_latency_ms), (200, 300));
+        assert_eq!(stats.max_turn_latency_ms, 300);
+    }
+
+    #[test]
+    fn long_sessions_estimate_percentiles() {
+        let mut stats = SessionStats::new("model");
+        // 1 to 1000 ms, in a scrambled order.
+        for i in 0..1000u64 {
+            stats.record_turn(&turn(i * 389 % 1000 + 1), None);
+        }
+        assert!(stats.p50_latency_ms.abs_diff(500) <= 20, "{}", stats.p50_latency_ms);
+        assert!(stats.p99_latency_ms.abs_diff(990) <= 10, "{}", stats.p99_latency_ms);
+    }
+
+    #[test]
+    fn percentiles_use_the_nearest_rank() {
+        let sorted: Vec<u64> = (1..=20).collect();
+        assert_eq!((percentile(&sorted, 50), percentile(&sorted, 95), percentile(&sorted, 100)), (10, 19, 20));
+        assert_eq!(percentile(&[], 50), 0);
+    }
+}
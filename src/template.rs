@@ -0,0 +1,307 @@
+//! `{{variable}}` substitution for prompt strings, with `{{#if key}}...{{/if}}`
+//! blocks that are kept only when `key` is present in the variables; and named
+//! message templates using `{variable}` and `{file:path}` placeholders.
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptTemplate(pub String);
+
+impl PromptTemplate {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read prompt template {}", path.display()))?;
+        Ok(Self(contents))
+    }
+
+    /// Renders the template, failing with the full list of placeholders that
+    /// had no value in `vars`.
+    pub fn render(&self, vars: &HashMap<&str, &str>) -> Result<String> {
+        let mut out = String::with_capacity(self.0.len());
+        let mut missing = Vec::new();
+        render_into(&self.0, vars, &mut out, &mut missing, false)?;
+        if !missing.is_empty() {
+            missing.sort_unstable();
+            missing.dedup();
+            anyhow::bail!("Unresolved template placeholders: {}", missing.join(", "));
+        }
+        Ok(out)
+    }
+
+    /// Renders the template, leaving placeholders with no value in `vars` as written.
+    pub fn render_allowing_missing(&self, vars: &HashMap<&str, &str>) -> Result<String> {
+        let mut out = String::with_capacity(self.0.len());
+        render_into(&self.0, vars, &mut out, &mut Vec::new(), true)?;
+        Ok(out)
+    }
+}
+
+// --- Message Templates ---
+
+/// Extension of the files in the templates directory.
+pub const MESSAGE_TEMPLATE_EXTENSION: &str = "txt";
+
+/// A reusable message from the templates directory, e.g. `summarize.txt`
+/// containing "Summarize {file:notes.md} focusing on {topic}".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageTemplate {
+    pub name: String,
+    pub text: String,
+}
+
+/// One `{...}` placeholder in a message template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Placeholder<'a> {
+    Var(&'a str),
+    File(&'a str),
+}
+
+/// Splits `text` into literal runs and placeholders. Braces around anything
+/// other than a variable name or `file:path` (e.g. JSON or code) stay literal.
+fn placeholders(text: &str) -> Vec<(std::ops::Range<usize>, Placeholder<'_>)> {
+    let is_name = |name: &str| {
+        name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    };
+    let mut found = Vec::new();
+    let mut pos = 0;
+    while let Some(start) = text[pos..].find('{').map(|i| pos + i) {
+        let Some(end) = text[start + 1..].find(['}', '{', '\n']).map(|i| start + 1 + i).filter(|&end| text[end..].starts_with('}')) else {
+            pos = start + 1;
+            continue;
+        };
+        let inner = &text[start + 1..end];
+        match inner.strip_prefix("file:") {
+            Some(path) if !path.trim().is_empty() => found.push((start..end + 1, Placeholder::File(path.trim()))),
+            None if is_name(inner) => found.push((start..end + 1, Placeholder::Var(inner))),
+            _ => {}
+        }
+        pos = end + 1;
+    }
+    found
+}
+
+impl MessageTemplate {
+    /// Reads `{dir}/{name}.txt`, suggesting a close name when there's no such template.
+    pub fn load(dir: &Path, name: &str) -> Result<Self> {
+        let path = dir.join(format!("{}.{}", name, MESSAGE_TEMPLATE_EXTENSION));
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Ok(Self { name: name.to_string(), text }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let names: Vec<String> = Self::list(dir)?.into_iter().map(|template| template.name).collect();
+                match crate::suggest::closest(name, names.iter().map(String::as_str)) {
+                    Some(suggestion) => anyhow::bail!("No template '{}' in {}. Did you mean '{}'?", name, dir.display(), suggestion),
+                    None => anyhow::bail!("No template '{}' in {}", name, dir.display()),
+                }
+            }
+            Err(e) => Err(e).with_context(|| format!("Failed to read template {}", path.display())),
+        }
+    }
+
+    /// Every template in `dir`, sorted by name. A missing directory has none.
+    pub fn list(dir: &Path) -> Result<Vec<Self>> {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read templates directory {}", dir.display())),
+        };
+        let mut templates = Vec::new();
+        for entry in entries {
+            let path: PathBuf = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(MESSAGE_TEMPLATE_EXTENSION) {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else { continue };
+            let text = std::fs::read_to_string(&path).with_context(|| format!("Failed to read template {}", path.display()))?;
+            templates.push(Self { name: name.to_string(), text });
+        }
+        templates.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(templates)
+    }
+
+    /// The placeholders the template uses, each once, in order of first use:
+    /// variable names as written, and files as `file:path`.
+    pub fn placeholders(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for (_, placeholder) in placeholders(&self.text) {
+            let name = match placeholder {
+                Placeholder::Var(name) => name.to_string(),
+                Placeholder::File(path) => format!("file:{}", path),
+            };
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        names
+    }
+
+    /// Substitutes `vars` and reads `{file:path}` placeholders from disk.
+    /// Fails listing the missing variables along with every one the template uses.
+    pub fn render(&self, vars: &HashMap<&str, &str>) -> Result<String> {
+        let found = placeholders(&self.text);
+        let mut missing: Vec<&str> = Vec::new();
+        for (_, placeholder) in &found {
+            if let Placeholder::Var(name) = placeholder {
+                if !vars.contains_key(name) && !missing.contains(name) {
+                    missing.push(name);
+                }
+            }
+        }
+        if !missing.is_empty() {
+            let required: Vec<String> = self.placeholders().into_iter().filter(|name| !name.starts_with("file:")).collect();
+            anyhow::bail!(
+                "Template '{}' is missing variables: {} (it uses: {}; pass them with --var KEY=VALUE)",
+                self.name,
+                missing.join(", "),
+                required.join(", ")
+            );
+        }
+
+        let mut out = String::with_capacity(self.text.len());
+        let mut last = 0;
+        for (range, placeholder) in found {
+            out.push_str(&self.text[last..range.start]);
+            match placeholder {
+                Placeholder::Var(name) => out.push_str(vars[name]),
+                Placeholder::File(path) => out.push_str(
+                    &std::fs::read_to_string(path)
+                        .with_context(|| format!("Failed to read {} for template '{}'", path, self.name))?,
+                ),
+            }
+            last = range.end;
+        }
+        out.push_str(&self.text[last..]);
+        Ok(out)
+    }
+}
+
+// --- Built-in Variables ---
+
+/// Variables every template can use: `date` (today in UTC, `YYYY-MM-DD`)
+/// and `cwd` (the current directory, when it can be read).
+pub fn builtin_vars() -> HashMap<&'static str, String> {
+    let mut vars = HashMap::new();
+    vars.insert("date", utc_date(SystemTime::now()));
+    if let Ok(cwd) = std::env::current_dir() {
+        vars.insert("cwd", cwd.display().to_string());
+    }
+    vars
+}
+
+/// `now` as a `YYYY-MM-DD` date in UTC.
+fn utc_date(now: SystemTime) -> String {
+    let secs = now.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()) as i64;
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Days since 1970-01-01 to a (year, month, day) civil date.
+pub(crate) fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (yoe + era * 400 + i64::from(month <= 2), month, day)
+}
+
+// --- Rendering ---
+
+/// Finds the `{{/if}}` closing the block whose body starts at `body`,
+/// skipping over nested `{{#if}}` blocks. Returns (body end, after closing tag).
+fn find_block_end(body: &str) -> Option<(usize, usize)> {
+    let mut depth = 0;
+    let mut pos = 0;
+    while let Some(start) = body[pos..].find("{{").map(|i| pos + i) {
+        let end = body[start..].find("}}").map(|i| start + i)?;
+        let tag = body[start + 2..end].trim();
+        if tag.starts_with("#if ") {
+            depth += 1;
+        } else if tag == "/if" {
+            if depth == 0 {
+                return Some((start, end + 2));
+            }
+            depth -= 1;
+        }
+        pos = end + 2;
+    }
+    None
+}
+
+fn render_into<'a>(
+    src: &'a str,
+    vars: &HashMap<&str, &str>,
+    out: &mut String,
+    missing: &mut Vec<&'a str>,
+    keep_missing: bool,
+) -> Result<()> {
+    let mut rest = src;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find("}}").map(|i| start + i) else {
+            anyhow::bail!("Unclosed '{{{{' in template");
+        };
+        let tag = rest[start + 2..end].trim();
+        let after = &rest[end + 2..];
+
+        if let Some(condition) = tag.strip_prefix("#if ") {
+            let Some((body_end, block_end)) = find_block_end(after) else {
+                anyhow::bail!("Missing {{{{/if}}}} for {{{{#if {}}}}}", condition.trim());
+            };
+            if vars.contains_key(condition.trim()) {
+                render_into(&after[..body_end], vars, out, missing, keep_missing)?;
+            }
+            rest = &after[block_end..];
+        } else if tag == "/if" {
+            anyhow::bail!("{{{{/if}}}} without a matching {{{{#if}}}}");
+        } else {
+            match vars.get(tag) {
+                Some(value) => out.push_str(value),
+                None if keep_missing => out.push_str(&rest[start..end + 2]),
+                None => missing.push(tag),
+            }
+            rest = after;
+        }
+    }
+    out.push_str(rest);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn missing_placeholders_fail_unless_allowed() {
+        let template = PromptTemplate("{{greeting}}, {{name}}".to_string());
+        let vars = HashMap::from([("greeting", "Hello")]);
+        assert!(template.render(&vars).is_err());
+        assert_eq!(template.render_allowing_missing(&vars).unwrap(), "Hello, {{name}}");
+    }
+
+    #[test]
+    fn message_templates_fill_variables_and_leave_other_braces() {
+        let template = MessageTemplate {
+            name: "summarize".to_string(),
+            text: "Summarize {stdin} focusing on {topic}, as {\"json\": true}. {topic}!".to_string(),
+        };
+        assert_eq!(template.placeholders(), vec!["stdin", "topic"]);
+        let error = template.render(&HashMap::from([("stdin", "notes")])).unwrap_err().to_string();
+        assert!(error.contains("missing variables: topic (it uses: stdin, topic"), "{}", error);
+        let vars = HashMap::from([("stdin", "notes"), ("topic", "risks")]);
+        assert_eq!(template.render(&vars).unwrap(), "Summarize notes focusing on risks, as {\"json\": true}. risks!");
+    }
+
+    #[test]
+    fn dates_are_utc_calendar_days() {
+        assert_eq!(utc_date(UNIX_EPOCH), "1970-01-01");
+        assert_eq!(utc_date(UNIX_EPOCH + Duration::from_secs(951_782_400)), "2000-02-29");
+    }
+}
@@ -0,0 +1,35 @@
+//! Rough token counts for requests before they're sent.
+
+use crate::llm::{Content, LLMRequest};
+
+/// Estimates token counts. Implement this to plug in a real tokenizer.
+pub trait TokenEstimator: Send + Sync {
+    fn estimate_text(&self, text: &str) -> u32;
+
+    /// Estimated input tokens for the whole request: system prompt plus every message.
+    fn estimate_request(&self, request: &LLMRequest) -> u32 {
+        let mut total = self.estimate_text(&request.system_prompt);
+        for message in &request.messages {
+            for part in &message.content {
+                total += match part {
+                    Content::Text(text) => self.estimate_text(text),
+                    Content::Image { .. } => IMAGE_TOKENS,
+                };
+            }
+        }
+        total
+    }
+}
+
+/// Upper bound the API charges for one image after resizing.
+pub const IMAGE_TOKENS: u32 = 1600;
+
+/// The common rule of thumb of about four characters per token for English text.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CharHeuristicEstimator;
+
+impl TokenEstimator for CharHeuristicEstimator {
+    fn estimate_text(&self, text: &str) -> u32 {
+        (text.chars().count() as u32).div_ceil(4)
+    }
+}
@@ -0,0 +1,74 @@
+//! Tools that agents can call, and the registry they are looked up in.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// What an agent is told about a tool.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: String,
+    /// JSON schema describing the tool's input.
+    pub input_schema: serde_json::Value,
+}
+
+#[async_trait]
+pub trait Tool: Send + Sync {
+    fn definition(&self) -> ToolDefinition;
+
+    /// Runs the tool on `input` and returns its output as text.
+    async fn call(&self, input: &str) -> Result<String>;
+}
+
+/// Tools available to an agent, keyed by name.
+#[derive(Default)]
+pub struct ToolRegistry {
+    tools: BTreeMap<String, Box<dyn Tool>>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a tool, replacing any existing tool with the same name.
+    pub fn register(&mut self, tool: Box<dyn Tool>) {
+        self.tools.insert(tool.definition().name, tool);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Tool> {
+        self.tools.get(name).map(Box::as_ref)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+
+    /// Definitions of every registered tool, sorted by name.
+    pub fn definitions(&self) -> Vec<ToolDefinition> {
+        self.tools.values().map(|tool| tool.definition()).collect()
+    }
+
+    /// A `- name: description` line per tool, for inclusion in prompts.
+    pub fn describe(&self) -> String {
+        self.definitions()
+            .iter()
+            .map(|d| format!("- {}: {}", d.name, d.description))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Calls the named tool, failing if it isn't registered.
+    pub async fn call(&self, name: &str, input: &str) -> Result<String> {
+        match self.get(name) {
+            Some(tool) => tool.call(input).await,
+            None => anyhow::bail!(
+                "Unknown tool '{}'. Available tools: {}",
+                name,
+                self.tools.keys().cloned().collect::<Vec<_>>().join(", ")
+            ),
+        }
+    }
+}
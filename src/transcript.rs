@@ -0,0 +1,44 @@
+//! Human-readable Markdown transcripts of a conversation, for sharing and archiving.
+
+use crate::llm::{Content, Message};
+
+/// Renders the conversation as Markdown. Message content is copied verbatim so
+/// fenced code blocks in responses survive intact.
+pub fn to_markdown(
+    system_prompt: &str,
+    messages: &[Message],
+    input_tokens: u32,
+    output_tokens: u32,
+    cost_usd: Option<f64>,
+) -> String {
+    let mut out = String::from("# Conversation Transcript\n\n");
+    for line in system_prompt.lines() {
+        out.push_str("> ");
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.push('\n');
+
+    for message in messages {
+        let speaker = if message.role == "user" { "You" } else { "Agent" };
+        out.push_str(&format!("**{}:**\n\n", speaker));
+        for part in &message.content {
+            match part {
+                Content::Text(text) => out.push_str(text.trim_end()),
+                Content::Image { media_type, .. } => out.push_str(&format!("*[image: {}]*", media_type)),
+            }
+            out.push_str("\n\n");
+        }
+    }
+
+    out.push_str("---\n\n");
+    out.push_str(&format!(
+        "Total tokens: {} in, {} out",
+        input_tokens, output_tokens
+    ));
+    match cost_usd {
+        Some(cost) => out.push_str(&format!(" | Cost: ${:.4}\n", cost)),
+        None => out.push_str(" | Cost: unknown\n"),
+    }
+    out
+}
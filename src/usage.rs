@@ -0,0 +1,93 @@
+//! A persistent log of API calls, one JSON record per line, for `cost` reports.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::llm::LLMResponse;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageRecord {
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub model: String,
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    /// `None` when the model had no known pricing.
+    pub cost_usd: Option<f64>,
+}
+
+impl UsageRecord {
+    /// A record of `response`, timestamped now.
+    pub fn new(model: &str, response: &LLMResponse, cost_usd: Option<f64>) -> Self {
+        Self {
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+            model: model.to_string(),
+            input_tokens: response.input_tokens,
+            output_tokens: response.output_tokens,
+            cost_usd,
+        }
+    }
+}
+
+/// Appends `record` to the log at `path`, creating it (and its directory) if needed.
+pub fn append(path: &Path, record: &UsageRecord) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open usage log {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(record)?)?;
+    Ok(())
+}
+
+/// Every record in the log at `path`; a missing log has none.
+pub fn read(path: &Path) -> Result<Vec<UsageRecord>> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read usage log {}", path.display())),
+    };
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("Invalid usage record on line {} of {}", i + 1, path.display()))
+        })
+        .collect()
+}
+
+/// Totals for one model across many records.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModelUsage {
+    pub requests: u32,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+    /// Requests whose cost is missing from `cost_usd` because pricing was unknown.
+    pub unpriced_requests: u32,
+}
+
+/// Sums `records` per model, sorted by model name.
+pub fn summarize<'a>(records: impl IntoIterator<Item = &'a UsageRecord>) -> BTreeMap<String, ModelUsage> {
+    let mut totals: BTreeMap<String, ModelUsage> = BTreeMap::new();
+    for record in records {
+        let usage = totals.entry(record.model.clone()).or_default();
+        usage.requests += 1;
+        usage.input_tokens += u64::from(record.input_tokens);
+        usage.output_tokens += u64::from(record.output_tokens);
+        match record.cost_usd {
+            Some(cost) => usage.cost_usd += cost,
+            None => usage.unpriced_requests += 1,
+        }
+    }
+    totals
+}
@@ -0,0 +1,88 @@
+//! A deliberately simple in-memory vector store for retrieval-augmented generation.
+//!
+//! Queries are a brute-force cosine-similarity scan over every entry, which is
+//! fine for thousands of chunks but not for millions. At production scale,
+//! switch to a dedicated store such as `qdrant-client` or `pgvector`.
+
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Anything that can be stored alongside an embedding and persisted as JSON.
+pub trait Metadata: Serialize + DeserializeOwned {}
+
+impl<T: Serialize + DeserializeOwned> Metadata for T {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "M: Metadata")]
+pub struct VectorStore<M: Metadata> {
+    entries: Vec<(Vec<f32>, M)>,
+}
+
+impl<M: Metadata> Default for VectorStore<M> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<M: Metadata> VectorStore<M> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn insert(&mut self, embedding: Vec<f32>, metadata: M) {
+        self.entries.push((embedding, metadata));
+    }
+
+    /// Returns up to `top_k` entries most similar to `embedding`, best match first.
+    pub fn query(&self, embedding: &[f32], top_k: usize) -> Vec<(f32, &M)> {
+        let mut scored: Vec<(f32, &M)> = self
+            .entries
+            .iter()
+            .map(|(vector, metadata)| (cosine_similarity(embedding, vector), metadata))
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored.truncate(top_k);
+        scored
+    }
+
+    /// Writes the store to `path` as JSON.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string(self).context("Failed to serialize vector store")?;
+        std::fs::write(path, json)
+            .with_context(|| format!("Failed to write vector store to {}", path.display()))
+    }
+
+    /// Reads a store previously written with `save`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read vector store from {}", path.display()))?;
+        serde_json::from_str(&json)
+            .with_context(|| format!("Failed to parse vector store {}", path.display()))
+    }
+}
+
+/// Cosine similarity of two vectors; 0.0 if either is all zeros or their lengths differ.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
@@ -0,0 +1,235 @@
+//! End-to-end checks of how subcommands and the legacy `-m`/`-i` flags pick
+//! between one-shot and interactive mode. Every run uses `--dry-run`, so no
+//! API key or network access is needed.
+
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+const BANNER: &str = "Interactive Mode";
+
+/// The binary with stdin at EOF, isolated from the user's config files in
+/// its own home directory.
+fn isolated(name: &str) -> (Command, PathBuf) {
+    let home: PathBuf = std::env::temp_dir().join(format!("ra1-cli-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&home).unwrap();
+    let mut command = Command::new(env!("CARGO_BIN_EXE_ra1"));
+    command
+        .current_dir(&home)
+        .env("HOME", &home)
+        .env("XDG_CONFIG_HOME", home.join("config"))
+        .env("XDG_DATA_HOME", home.join("data"))
+        .env_remove("NO_COLOR")
+        .env("RUST_BACKTRACE", "0")
+        .stdin(Stdio::null());
+    (command, home)
+}
+
+/// Runs the binary in a dry run.
+fn run(name: &str, args: &[&str]) -> Output {
+    isolated(name).0.arg("--dry-run").args(args).output().unwrap()
+}
+
+/// Serves one HTTP request with `body` as a server-sent event stream,
+/// returning the base URL and a handle to the raw request.
+fn mock_stream(body: &'static str) -> (String, std::thread::JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let handle = std::thread::spawn(move || {
+        let (mut socket, _) = listener.accept().unwrap();
+        let mut raw = Vec::new();
+        let mut buf = [0; 4096];
+        loop {
+            let n = socket.read(&mut buf).unwrap();
+            raw.extend_from_slice(&buf[..n]);
+            let text = String::from_utf8_lossy(&raw).to_string();
+            if let Some(header_end) = text.find("\r\n\r\n") {
+                let length = text[..header_end]
+                    .lines()
+                    .find_map(|line| line.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                    .unwrap_or(0);
+                if raw.len() >= header_end + 4 + length {
+                    break;
+                }
+            }
+        }
+        let response = format!("HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n{}", body);
+        socket.write_all(response.as_bytes()).unwrap();
+        String::from_utf8(raw).unwrap()
+    });
+    (url, handle)
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+/// The requests a dry-run chat session echoed after the banner, parsed from
+/// each `Agent:` reply up to its token footer.
+fn chat_requests(stdout: &str) -> Vec<serde_json::Value> {
+    let after_banner = &stdout[stdout.find(BANNER).expect("no interactive banner")..];
+    after_banner
+        .split("Agent: ")
+        .skip(1)
+        .map(|reply| serde_json::from_str(&reply[..reply.find("└─").expect("no token footer")]).expect("reply is not a request"))
+        .collect()
+}
+
+/// The text of the first message in `request`.
+fn first_message_text(request: &serde_json::Value) -> &str {
+    request["messages"][0]["content"][0]["text"].as_str().expect("no text in the first message")
+}
+
+#[test]
+fn message_alone_is_one_shot() {
+    let output = run("one-shot", &["-m", "hello there"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = stdout(&output);
+    assert!(stdout.contains("hello there"), "{}", stdout);
+    assert!(!stdout.contains(BANNER), "{}", stdout);
+}
+
+#[test]
+fn interactive_alone_opens_the_chat_loop() {
+    let output = run("interactive", &["-i"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = stdout(&output);
+    assert!(stdout.contains(BANNER), "{}", stdout);
+    // EOF on stdin ends the session before any turn is sent.
+    assert!(!stdout.contains("\"messages\""), "{}", stdout);
+}
+
+#[test]
+fn message_with_interactive_seeds_the_chat_loop() {
+    let output = run("seeded", &["-m", "hello there", "-i"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = stdout(&output);
+    let requests = chat_requests(&stdout);
+    assert_eq!(requests.len(), 1, "{}", stdout);
+    assert_eq!(first_message_text(&requests[0]), "hello there");
+    assert!(stdout.contains("Session Summary"), "{}", stdout);
+}
+
+#[test]
+fn ask_subcommand_is_one_shot() {
+    let output = run("ask", &["ask", "hello there"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = stdout(&output);
+    assert!(stdout.contains("hello there"), "{}", stdout);
+    assert!(!stdout.contains(BANNER), "{}", stdout);
+}
+
+#[test]
+fn ask_json_prints_one_object() {
+    let output = run("ask-json", &["ask", "--json", "hello there"]);
+    assert!(output.status.success(), "{:?}", output);
+    let value: serde_json::Value = serde_json::from_slice(&output.stdout).expect("stdout is not one JSON object");
+    assert!(value["content"].as_str().unwrap().contains("hello there"));
+    assert!(value["model"].is_string());
+}
+
+#[test]
+fn chat_subcommand_with_message_seeds_the_chat_loop() {
+    let output = run("chat", &["chat", "hello there"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = stdout(&output);
+    let requests = chat_requests(&stdout);
+    assert_eq!(requests.len(), 1, "{}", stdout);
+    assert_eq!(first_message_text(&requests[0]), "hello there");
+}
+
+#[test]
+fn chat_flags_are_rejected_by_ask() {
+    let output = run("ask-flags", &["ask", "--typewriter", "hello"]);
+    assert!(!output.status.success(), "{:?}", output);
+}
+
+#[test]
+fn blocked_patterns_keep_a_message_from_being_sent() {
+    let home = std::env::temp_dir().join(format!("ra1-cli-blocked-{}", std::process::id()));
+    std::fs::create_dir_all(home.join(".ra1")).unwrap();
+    std::fs::write(home.join(".ra1/config.toml"), "blocked_patterns = ['(?i)password']\n").unwrap();
+    let output = run("blocked", &["-m", "my Password is hunter2"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(!stdout(&output).contains("hunter2"), "{:?}", output);
+    assert!(stdout(&output).starts_with("Request blocked by guardrail: a message matches blocked pattern '(?i)password'"), "{:?}", output);
+}
+
+#[test]
+fn prefill_is_rejected_with_reflect() {
+    for args in [&["ask", "--prefill", "{", "--reflect", "1", "hi"][..], &["--reflect", "1", "ask", "--prefill", "{", "hi"], &["-m", "hi", "--prefill", "{", "--reflect", "1"]] {
+        let output = run("prefill-reflect", args);
+        assert_eq!(output.status.code(), Some(2), "{:?}", output);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("--reflect"), "{:?}", output);
+    }
+}
+
+#[test]
+fn script_runs_are_not_saved() {
+    for args in [&["--script", "turns.jsonl", "--save"][..], &["chat", "--script", "turns.jsonl", "--tag", "work"]] {
+        let output = run("script-save", args);
+        assert_eq!(output.status.code(), Some(2), "{:?}", output);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("--script"), "{:?}", output);
+    }
+}
+
+#[test]
+fn stream_is_rejected_with_json() {
+    for args in [&["ask", "--json", "--stream", "hi"][..], &["--stream", "ask", "--json", "hi"]] {
+        let output = run("stream-json", args);
+        assert_eq!(output.status.code(), Some(2), "{:?}", output);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("--stream can't be used with --json"), "{:?}", output);
+    }
+}
+
+#[test]
+fn stream_flag_streams_the_reply() {
+    let events = "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":5,\"output_tokens\":1}}}\n\n\
+        event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"streamed reply\"}}\n\n\
+        event: message_delta\ndata: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\",\"stop_sequence\":null},\"usage\":{\"output_tokens\":2}}\n\n\
+        event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n";
+    let (url, server) = mock_stream(events);
+    let (mut command, home) = isolated("stream");
+    std::fs::write(home.join("key"), "sk-test\n").unwrap();
+    let output = command
+        .args(["ask", "--stream", "hi"])
+        .env("CLAUDE_AGENT_API_BASE_URL", &url)
+        .env("CLAUDE_AGENT_KEY_FILE_PATH", home.join("key"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(stdout(&output).contains("streamed reply"), "{:?}", output);
+    assert!(server.join().unwrap().contains("\"stream\":true"));
+}
+
+#[test]
+fn configured_system_prompts_are_only_templates_with_vars() {
+    let (_, home) = isolated("system-template");
+    std::fs::create_dir_all(home.join(".ra1")).unwrap();
+    std::fs::write(home.join(".ra1/config.toml"), "system_prompt = 'Answer in {{lang}}; escape {{ in code.'\n").unwrap();
+    let literal = run("system-template", &["ask", "--json", "hi"]);
+    assert!(literal.status.success(), "{:?}", literal);
+    assert!(stdout(&literal).contains("escape {{ in code"), "{:?}", literal);
+
+    let rendered = run("system-template", &["ask", "--var", "lang=French", "hi"]);
+    assert!(!rendered.status.success(), "an unclosed '{{{{' should fail once rendered: {:?}", rendered);
+    std::fs::write(home.join(".ra1/config.toml"), "system_prompt = 'Answer in {{lang}}.'\n").unwrap();
+    let rendered = run("system-template", &["ask", "--var", "lang=French", "hi"]);
+    assert!(stdout(&rendered).contains("Answer in French."), "{:?}", rendered);
+}
+
+#[test]
+fn raw_is_rejected_with_record() {
+    let (mut command, home) = isolated("raw-record");
+    std::fs::write(home.join("key"), "sk-test\n").unwrap();
+    let output = command
+        .args(["--record", "cassette.json", "ask", "--raw", "hi"])
+        .env("CLAUDE_AGENT_API_BASE_URL", "http://127.0.0.1:9")
+        .env("CLAUDE_AGENT_KEY_FILE_PATH", home.join("key"))
+        .output()
+        .unwrap();
+    assert!(!output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("can't be used with --record"), "{:?}", output);
+    assert!(!home.join("cassette.json").exists());
+}
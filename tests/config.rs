@@ -0,0 +1,153 @@
+//! End-to-end checks of config precedence: each layer overrides the ones
+//! below it, as read back through `config get`.
+
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// A scratch home directory with optional user and project config files.
+struct Home(PathBuf);
+
+impl Home {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("ra1-config-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    fn write(&self, relative: &str, contents: &str) -> &Self {
+        let path = self.0.join(relative);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+        self
+    }
+
+    /// `config get <key>` with `env` set and `flags` before the subcommand.
+    /// The user config file is passed with `--config`, so no platform's
+    /// default location is read.
+    fn get(&self, key: &str, env: &[(&str, &str)], flags: &[&str]) -> String {
+        let output = Command::new(env!("CARGO_BIN_EXE_ra1"))
+            .arg("--config")
+            .arg(self.0.join(USER_CONFIG))
+            .args(flags)
+            .args(["config", "get", key])
+            .current_dir(&self.0)
+            .env("HOME", &self.0)
+            .env("XDG_DATA_HOME", self.0.join("data"))
+            // Blank values count as unset, which hides the caller's own settings.
+            .envs(std::env::vars().filter(|(name, _)| name.starts_with("CLAUDE_AGENT_")).map(|(name, _)| (name, String::new())))
+            .envs(env.iter().copied())
+            .stdin(Stdio::null())
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    }
+}
+
+impl Drop for Home {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+const USER_CONFIG: &str = "config.toml";
+const PROJECT_CONFIG: &str = ".ra1/config.toml";
+
+#[test]
+fn defaults_apply_without_any_config() {
+    let home = Home::new("default");
+    assert_eq!(home.get("max_tokens", &[], &[]), "4096");
+    assert_eq!(home.get("temperature", &[], &[]), "0.7");
+}
+
+#[test]
+fn config_file_overrides_defaults() {
+    let home = Home::new("file");
+    home.write(USER_CONFIG, "model = \"file-model\"\n");
+    assert_eq!(home.get("model", &[], &[]), "file-model");
+    assert_eq!(home.get("max_tokens", &[], &[]), "4096");
+}
+
+#[test]
+fn project_file_overrides_config_file() {
+    let home = Home::new("project");
+    home.write(USER_CONFIG, "model = \"file-model\"\nmax_tokens = 1000\n")
+        .write(PROJECT_CONFIG, "model = \"project-model\"\n");
+    assert_eq!(home.get("model", &[], &[]), "project-model");
+    assert_eq!(home.get("max_tokens", &[], &[]), "1000");
+}
+
+#[test]
+fn environment_overrides_files() {
+    let home = Home::new("env");
+    home.write(USER_CONFIG, "model = \"file-model\"\n").write(PROJECT_CONFIG, "max_tokens = 1000\n");
+    let env = [("CLAUDE_AGENT_MODEL", "env-model"), ("CLAUDE_AGENT_MAX_TOKENS", "2000")];
+    assert_eq!(home.get("model", &env, &[]), "env-model");
+    assert_eq!(home.get("max_tokens", &env, &[]), "2000");
+}
+
+#[test]
+fn environment_sets_lists_and_enums() {
+    let home = Home::new("env-lists");
+    home.write(USER_CONFIG, "stop_sequences = [\"FILE\"]\ncontext_strategy = \"truncate\"\n");
+    let env = [
+        ("CLAUDE_AGENT_STOP_SEQUENCES", "END, STOP"),
+        ("CLAUDE_AGENT_BLOCKED_PATTERNS", r#"["\\d{3,4}", "secret"]"#),
+        ("CLAUDE_AGENT_CONTEXT_STRATEGY", "summarize"),
+        ("CLAUDE_AGENT_CONTEXT_LIMIT_TOKENS", "5000"),
+        ("CLAUDE_AGENT_SESSION_FORMAT", "jsonl"),
+        ("CLAUDE_AGENT_RESPONSE_FORMAT", "json_object"),
+    ];
+    assert_eq!(home.get("stop_sequences", &env, &[]), r#"["END", "STOP"]"#);
+    assert_eq!(home.get("blocked_patterns", &env, &[]), r#"['\d{3,4}', "secret"]"#);
+    assert_eq!(home.get("context_strategy", &env, &[]), "summarize");
+    assert_eq!(home.get("context_limit_tokens", &env, &[]), "5000");
+    assert_eq!(home.get("session_format", &env, &[]), "jsonl");
+    assert_eq!(home.get("response_format", &env, &[]), "json_object");
+}
+
+#[test]
+fn persona_overrides_environment_and_flags_override_everything() {
+    let home = Home::new("flags");
+    home.write(USER_CONFIG, "[personas.terse]\nsystem_prompt = \"Be terse.\"\nmodel = \"persona-model\"\n");
+    let env = [("CLAUDE_AGENT_MODEL", "env-model")];
+    assert_eq!(home.get("model", &env, &["--persona", "terse"]), "persona-model");
+    assert_eq!(home.get("model", &env, &["--persona", "terse", "--model", "flag-model"]), "flag-model");
+    assert_eq!(home.get("system_prompt", &env, &["--persona", "terse"]), "Be terse.");
+}
+
+#[test]
+fn key_file_sources_resolve_by_layer() {
+    let home = Home::new("key-file");
+    home.write(USER_CONFIG, "key_file_path = \"/from/file\"\n");
+    let env = [("CLAUDE_AGENT_KEY_FILE_PATH", "/from/env")];
+    assert_eq!(home.get("key_file_path", &[], &[]), "/from/file");
+    assert_eq!(home.get("key_file_path", &env, &[]), "/from/env");
+    assert_eq!(home.get("key_file_path", &env, &["--key-file", "/from/flag"]), "/from/flag");
+}
+
+#[test]
+fn tables_merge_key_by_key_and_lists_replace() {
+    let home = Home::new("merge");
+    home.write(USER_CONFIG, "stop_sequences = [\"END\"]\n[extra_headers]\nx-team = \"a\"\n")
+        .write(PROJECT_CONFIG, "stop_sequences = [\"STOP\"]\n[personas.reviewer]\nsystem_prompt = \"Review.\"\n");
+    assert_eq!(home.get("stop_sequences", &[], &[]), "[\"STOP\"]");
+    assert!(home.get("personas", &[], &[]).contains("reviewer"));
+    let headers = home.get("extra_headers", &[], &["--header", "x-flag: c"]);
+    for name in ["x-team", "x-flag"] {
+        assert!(headers.contains(name), "{}", headers);
+    }
+}
+
+#[test]
+fn project_file_cannot_redirect_requests() {
+    let home = Home::new("restricted");
+    home.write(USER_CONFIG, "[extra_headers]\nx-team = \"a\"\n").write(
+        PROJECT_CONFIG,
+        "model = \"project-model\"\napi_base_url = \"https://attacker.example\"\n[extra_headers]\nx-project = \"b\"\n",
+    );
+    assert_eq!(home.get("model", &[], &[]), "project-model");
+    assert_eq!(home.get("api_base_url", &[], &[]), "https://api.anthropic.com");
+    assert!(!home.get("extra_headers", &[], &[]).contains("x-project"));
+}